@@ -0,0 +1,200 @@
+//!
+//! `#[derive(CustomHeader)]`：根据结构体的字段生成RocketMQ请求和响应头部的编码和解码。
+//!
+//! 字段名默认转换为驼峰作为extFields的key，`#[header(rename = "...")]`指定其他的key；
+//! `Option`类型的字段是可选的，编码的时候跳过None，解码的时候缺少的字段为None；
+//! 其他字段解码的时候缺少或者解析失败返回`HeaderError`。字段的类型需要实现`ToString`和`FromStr`。
+//!
+//! 生成的代码通过`::rocketmq_exporter_rust`引用库，依赖被重命名或者通过其他crate重新导出的时候，
+//! 在结构体上使用`#[header(crate = "...")]`指定库的路径
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Error, Field, Fields,
+    GenericArgument, LitStr, Path, PathArguments, Type,
+};
+
+#[proc_macro_derive(CustomHeader, attributes(header))]
+pub fn derive_custom_header(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let krate = crate_path(&input.attrs)?;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "CustomHeader can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut encodes = vec![];
+    let mut decodes = vec![];
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        let key = header_key(field)?;
+        match option_inner(&field.ty) {
+            Some(inner) => {
+                encodes.push(quote! {
+                    if let Some(value) = &self.#ident {
+                        data.insert(#key.to_string(), value.to_string());
+                    }
+                });
+                decodes.push(quote! {
+                    #ident: match ext_fields.get(#key) {
+                        Some(value) => Some(value.parse::<#inner>().map_err(|_| {
+                            #krate::cmd::command::HeaderError::InvalidField {
+                                name: #key,
+                                value: value.clone(),
+                            }
+                        })?),
+                        None => None,
+                    }
+                });
+            }
+            None => {
+                let ty = &field.ty;
+                encodes.push(quote! {
+                    data.insert(#key.to_string(), self.#ident.to_string());
+                });
+                decodes.push(quote! {
+                    #ident: {
+                        let value = ext_fields.get(#key).ok_or(
+                            #krate::cmd::command::HeaderError::MissingField(#key),
+                        )?;
+                        value.parse::<#ty>().map_err(|_| {
+                            #krate::cmd::command::HeaderError::InvalidField {
+                                name: #key,
+                                value: value.clone(),
+                            }
+                        })?
+                    }
+                });
+            }
+        }
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::cmd::command::CustomHeader
+            for #name #ty_generics #where_clause
+        {
+            fn encode(&self) -> ::std::collections::HashMap<String, String> {
+                let mut data = ::std::collections::HashMap::new();
+                #(#encodes)*
+                data
+            }
+
+            fn decode(
+                ext_fields: &::std::collections::HashMap<String, String>,
+            ) -> Result<Self, #krate::cmd::command::HeaderError> {
+                Ok(#name {
+                    #(#decodes,)*
+                })
+            }
+        }
+    })
+}
+
+///
+/// 库的路径，默认是::rocketmq_exporter_rust
+fn crate_path(attrs: &[Attribute]) -> syn::Result<Path> {
+    let mut krate = None;
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("header") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                let value: LitStr = meta.value()?.parse()?;
+                krate = Some(value.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported header attribute, expected `crate`"))
+            }
+        })?;
+    }
+    Ok(krate.unwrap_or_else(|| syn::parse_quote!(::rocketmq_exporter_rust)))
+}
+
+///
+/// 字段在extFields中的key，没有rename的时候把字段名转换为驼峰
+fn header_key(field: &Field) -> syn::Result<String> {
+    let mut rename = None;
+    for attr in field.attrs.iter() {
+        if !attr.path().is_ident("header") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                rename = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported header attribute, expected `rename`"))
+            }
+        })?;
+    }
+    let ident = field.ident.as_ref().unwrap().to_string();
+    Ok(rename.unwrap_or_else(|| camel_case(ident.trim_start_matches("r#"))))
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = !out.is_empty();
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+///
+/// Option<T>的T，其他类型返回None
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("consumer_group"), "consumerGroup");
+        assert_eq!(camel_case("topic"), "topic");
+        assert_eq!(camel_case("_unit_mode"), "unitMode");
+    }
+}
@@ -0,0 +1,557 @@
+//!
+//! 和mqadmin类似的命令行子命令，复用Client查询集群，输出对齐的表格或者JSON
+
+use std::{collections::HashSet, path::PathBuf};
+
+use chrono::{DateTime, Local};
+use clap::{Parser, Subcommand};
+
+use rocketmq_exporter_rust::{
+    remoting::{client::Client, response::WindowTps},
+    util::{table::Table, topic::TopicType},
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "RocketMQ exporter and admin tool")]
+pub struct Cli {
+    ///
+    /// Nameserver的地址，多个地址用;分隔，没有指定的时候使用环境变量ROCKETMQ_NAMESRV_ADDR
+    #[arg(short = 'n', long, global = true)]
+    pub namesrv_addr: Option<String>,
+
+    ///
+    /// 输出JSON格式，每一行是一个以列名为key的对象
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    ///
+    /// 把和集群之间所有的请求和响应录制到文件中，每一行是一个JSON
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    ///
+    /// 不连接集群，回放--record录制的文件，需要开启mock feature
+    #[cfg(feature = "mock")]
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    ///
+    /// 没有子命令的时候启动Exporter
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(rename_all = "camelCase")]
+pub enum Command {
+    ///
+    /// 集群中所有Broker节点的版本和TPS
+    ClusterList,
+    ///
+    /// 所有的Topic和类型
+    TopicList,
+    ///
+    /// Topic在每个Broker上的队列数量、权限和地址
+    TopicRoute {
+        #[arg(short = 't', long)]
+        topic: String,
+    },
+    ///
+    /// Topic每个队列的offset
+    TopicStatus {
+        #[arg(short = 't', long)]
+        topic: String,
+    },
+    ///
+    /// 消费者组每个队列的消费进度，没有指定Topic的时候查询所有订阅的Topic
+    ConsumerProgress {
+        #[arg(short = 'g', long)]
+        group: String,
+        #[arg(short = 't', long)]
+        topic: Option<String>,
+    },
+    ///
+    /// 消费者组的在线客户端
+    ConsumerConnection {
+        #[arg(short = 'g', long)]
+        group: String,
+    },
+    ///
+    /// Broker的所有运行时信息
+    BrokerStatus {
+        #[arg(short = 'b', long)]
+        broker_addr: String,
+    },
+    ///
+    /// 根据offset消息ID查询消息
+    QueryMsgById {
+        #[arg(short = 'i', long)]
+        msg_id: String,
+    },
+    ///
+    /// 打印YAML描述的期望状态和集群实际状态的差异
+    #[cfg(feature = "admin")]
+    Plan { file: String },
+    ///
+    /// 确认以后把YAML描述的期望状态应用到集群
+    #[cfg(feature = "admin")]
+    Apply {
+        file: String,
+        ///
+        /// 不需要确认
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+///
+/// 执行子命令，返回进程的退出码
+pub async fn run(client: &mut Client, command: Command, json: bool) -> i32 {
+    match command {
+        Command::ClusterList => cluster_list(client, json).await,
+        Command::TopicList => topic_list(client, json).await,
+        Command::TopicRoute { topic } => topic_route(client, topic, json).await,
+        Command::TopicStatus { topic } => topic_status(client, topic, json).await,
+        Command::ConsumerProgress { group, topic } => {
+            consumer_progress(client, group, topic, json).await
+        }
+        Command::ConsumerConnection { group } => consumer_connection(client, group, json).await,
+        Command::BrokerStatus { broker_addr } => broker_status(client, &broker_addr, json).await,
+        Command::QueryMsgById { msg_id } => query_msg_by_id(client, &msg_id, json).await,
+        #[cfg(feature = "admin")]
+        Command::Plan { file } => reconcile_cluster(client, &file, false, false).await,
+        #[cfg(feature = "admin")]
+        Command::Apply { file, yes } => reconcile_cluster(client, &file, true, yes).await,
+    }
+}
+
+///
+/// 毫秒时间戳格式化为本地时间，0表示没有时间
+fn format_timestamp(timestamp: i64) -> String {
+    if timestamp <= 0 {
+        return String::from("-");
+    }
+    match DateTime::from_timestamp_millis(timestamp) {
+        Some(time) => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+///
+/// Nameserver请求失败的时候打印错误，返回退出码
+fn nameserver_failed(client: &Client) -> i32 {
+    eprintln!("Failed to request nameserver {}", client.namesrv_addr());
+    1
+}
+
+async fn cluster_list(client: &mut Client, json: bool) -> i32 {
+    let broker_info = match client.broker_info().await {
+        Some(broker_info) => broker_info,
+        None => return nameserver_failed(client),
+    };
+    let mut table = Table::new(&[
+        "cluster",
+        "broker",
+        "brokerId",
+        "addr",
+        "version",
+        "putTps",
+        "getTransferedTps",
+        "putTotalToday",
+        "getTotalToday",
+    ]);
+    for broker in broker_info.brokers() {
+        let mut addrs: Vec<_> = broker.broker_addrs().iter().collect();
+        addrs.sort();
+        for (broker_id, addr) in addrs {
+            let mut row = vec![
+                broker.cluster().to_string(),
+                broker.broker_name().to_string(),
+                broker_id.to_string(),
+                addr.clone(),
+            ];
+            if let Some(info) = client.broker_runtime_info(addr).await {
+                let tps = |tps: Option<WindowTps>| {
+                    tps.map(|t| format!("{:.2}", t.windows()[0].1))
+                        .unwrap_or_default()
+                };
+                let entry = |key: &str| info.entries().get(key).cloned().unwrap_or_default();
+                row.extend([
+                    entry("brokerVersionDesc"),
+                    tps(info.table().put_tps()),
+                    tps(info.table().get_transfered_tps()),
+                    entry("msgPutTotalTodayNow"),
+                    entry("msgGetTotalTodayNow"),
+                ]);
+            }
+            table.add_row(row);
+        }
+    }
+    table.print(json);
+    0
+}
+
+async fn topic_list(client: &mut Client, json: bool) -> i32 {
+    let system_topics: HashSet<String> = client
+        .system_topic_list()
+        .await
+        .map(|t| t.topics().iter().cloned().collect())
+        .unwrap_or_default();
+    let mut topics = match client.topic_list().await {
+        Some(topics) => topics.topics().clone(),
+        None => return nameserver_failed(client),
+    };
+    topics.sort();
+    let mut table = Table::new(&["topic", "type"]);
+    for topic in topics {
+        let topic_type = TopicType::classify(&topic, &system_topics);
+        table.add_row(vec![topic, topic_type.as_str().to_string()]);
+    }
+    table.print(json);
+    0
+}
+
+async fn topic_route(client: &mut Client, topic: String, json: bool) -> i32 {
+    let route = match client.topic_route(topic.clone()).await {
+        Some(route) => route,
+        None => {
+            eprintln!("No route info of topic {topic}");
+            return 1;
+        }
+    };
+    let mut table = Table::new(&[
+        "broker",
+        "readQueueNums",
+        "writeQueueNums",
+        "perm",
+        "brokerAddrs",
+    ]);
+    let mut queues: Vec<_> = route.queues().iter().collect();
+    queues.sort_by(|a, b| a.broker_name().cmp(b.broker_name()));
+    for queue in queues {
+        let mut addrs: Vec<String> = route
+            .brokers()
+            .iter()
+            .filter(|b| b.broker_name() == queue.broker_name())
+            .flat_map(|b| b.broker_addrs().iter())
+            .map(|(id, addr)| format!("{id}={addr}"))
+            .collect();
+        addrs.sort();
+        table.add_row(vec![
+            queue.broker_name().to_string(),
+            queue.read_queue_nums().to_string(),
+            queue.write_queue_nums().to_string(),
+            queue.perm().to_string(),
+            addrs.join(","),
+        ]);
+    }
+    table.print(json);
+    0
+}
+
+async fn topic_status(client: &mut Client, topic: String, json: bool) -> i32 {
+    let route = match client.topic_route(topic.clone()).await {
+        Some(route) => route,
+        None => {
+            eprintln!("No route info of topic {topic}");
+            return 1;
+        }
+    };
+    let addrs: Vec<String> = route
+        .brokers()
+        .iter()
+        .filter_map(|b| b.master_broker_addrs().cloned())
+        .collect();
+    let mut offsets = vec![];
+    for addr in addrs {
+        if let Some(stats) = client.topic_stats(&addr, topic.clone()).await {
+            for (mq, offset) in stats.offset_table() {
+                offsets.push((
+                    mq.broker_name().to_string(),
+                    mq.queue_id(),
+                    offset.min_offset(),
+                    offset.max_offset(),
+                    offset.last_update_timestamp(),
+                ));
+            }
+        }
+    }
+    offsets.sort();
+    let mut table = Table::new(&["broker", "queueId", "minOffset", "maxOffset", "lastUpdated"]);
+    for (broker, queue_id, min, max, last_update) in offsets {
+        table.add_row(vec![
+            broker,
+            queue_id.to_string(),
+            min.to_string(),
+            max.to_string(),
+            format_timestamp(last_update),
+        ]);
+    }
+    table.print(json);
+    0
+}
+
+async fn consumer_progress(
+    client: &mut Client,
+    group: String,
+    topic: Option<String>,
+    json: bool,
+) -> i32 {
+    let broker_info = match client.broker_info().await {
+        Some(broker_info) => broker_info,
+        None => return nameserver_failed(client),
+    };
+    let addrs: Vec<String> = broker_info
+        .master_brokers()
+        .iter()
+        .filter_map(|b| b.master_broker_addrs().cloned())
+        .collect();
+    let mut offsets = vec![];
+    for addr in addrs {
+        let topic = topic.clone().unwrap_or_default();
+        if let Some(stats) = client.consume_stats(&addr, group.clone(), topic).await {
+            for (mq, offset) in stats.offset_table() {
+                offsets.push((
+                    mq.topic().to_string(),
+                    mq.broker_name().to_string(),
+                    mq.queue_id(),
+                    offset.broker_offset(),
+                    offset.consumer_offset(),
+                    offset.backlog(),
+                    offset.last_timestamp(),
+                ));
+            }
+        }
+    }
+    if offsets.is_empty() {
+        eprintln!("No consume progress of group {group}");
+        return 1;
+    }
+    offsets.sort();
+    let total: i64 = offsets.iter().map(|o| o.5).sum();
+    let mut table = Table::new(&[
+        "topic",
+        "broker",
+        "queueId",
+        "brokerOffset",
+        "consumerOffset",
+        "diff",
+        "lastTime",
+    ]);
+    for (topic, broker, queue_id, broker_offset, consumer_offset, diff, last_time) in offsets {
+        table.add_row(vec![
+            topic,
+            broker,
+            queue_id.to_string(),
+            broker_offset.to_string(),
+            consumer_offset.to_string(),
+            diff.to_string(),
+            format_timestamp(last_time),
+        ]);
+    }
+    table.print(json);
+    if !json {
+        println!("\nDiff Total: {total}");
+    }
+    0
+}
+
+async fn consumer_connection(client: &mut Client, group: String, json: bool) -> i32 {
+    let broker_info = match client.broker_info().await {
+        Some(broker_info) => broker_info,
+        None => return nameserver_failed(client),
+    };
+    let addrs: Vec<String> = broker_info
+        .master_brokers()
+        .iter()
+        .filter_map(|b| b.master_broker_addrs().cloned())
+        .collect();
+    for addr in addrs {
+        let connection = match client.consumer_connection(&addr, group.clone()).await {
+            Some(connection) if !connection.connections().is_empty() => connection,
+            _ => continue,
+        };
+        let mut table = Table::new(&["clientId", "clientAddr", "language", "version"]);
+        for c in connection.connections() {
+            table.add_row(vec![
+                c.client_id().to_string(),
+                c.client_addr().to_string(),
+                c.language().to_string(),
+                c.version().to_string(),
+            ]);
+        }
+        table.print(json);
+        if !json {
+            println!(
+                "\nConsumeType: {}\nMessageModel: {}",
+                connection.consume_type(),
+                connection.message_model()
+            );
+        }
+        return 0;
+    }
+    eprintln!("Consumer group {group} is not online");
+    1
+}
+
+async fn broker_status(client: &mut Client, addr: &str, json: bool) -> i32 {
+    let info = match client.broker_runtime_info(addr).await {
+        Some(info) => info,
+        None => {
+            eprintln!("Failed to get runtime info of broker {addr}");
+            return 1;
+        }
+    };
+    let mut table = Table::new(&["key", "value"]);
+    for (key, value) in info.entries() {
+        table.add_row(vec![key.clone(), value.clone()]);
+    }
+    table.print(json);
+    0
+}
+
+async fn query_msg_by_id(client: &mut Client, msg_id: &str, json: bool) -> i32 {
+    let message = match client.view_message_by_id(msg_id).await {
+        Some(message) => message,
+        None => {
+            eprintln!("Message {msg_id} not found");
+            return 1;
+        }
+    };
+    let mut properties: Vec<String> = message
+        .properties()
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect();
+    properties.sort();
+    let mut table = Table::new(&["key", "value"]);
+    for (key, value) in [
+        ("OffsetID", message.offset_msg_id().to_string()),
+        ("Topic", message.topic().to_string()),
+        ("Tags", message.tags().unwrap_or_default().to_string()),
+        ("Keys", message.keys().join(" ")),
+        ("Queue ID", message.queue_id().to_string()),
+        ("Queue Offset", message.queue_offset().to_string()),
+        ("CommitLog Offset", message.commit_log_offset().to_string()),
+        ("Reconsume Times", message.reconsume_times().to_string()),
+        ("Born Timestamp", format_timestamp(message.born_timestamp())),
+        (
+            "Store Timestamp",
+            format_timestamp(message.store_timestamp()),
+        ),
+        ("Born Host", message.born_host().to_string()),
+        ("Store Host", message.store_host().to_string()),
+        ("System Flag", message.sys_flag().to_string()),
+        ("Properties", properties.join(" ")),
+        ("Body", String::from_utf8_lossy(message.body()).into_owned()),
+    ] {
+        table.add_row(vec![key.to_string(), value]);
+    }
+    table.print(json);
+    0
+}
+
+///
+/// 打印期望状态和实际状态的差异，apply为true的时候在确认以后执行，返回进程的退出码
+#[cfg(feature = "admin")]
+async fn reconcile_cluster(client: &mut Client, path: &str, apply: bool, yes: bool) -> i32 {
+    use crate::reconcile;
+
+    let state = match reconcile::DesiredState::load(path) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+    let changes = match reconcile::plan(client, &state).await {
+        Ok(changes) => changes,
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    };
+    if changes.is_empty() {
+        println!("No changes, the cluster matches {path}");
+        return 0;
+    }
+    for change in changes.iter() {
+        println!("{change}");
+    }
+    if !apply {
+        return 0;
+    }
+    if !yes {
+        println!(
+            "Apply {} change(s)? Only 'yes' will be accepted:",
+            changes.len()
+        );
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err() || answer.trim() != "yes" {
+            println!("Apply cancelled");
+            return 1;
+        }
+    }
+    if reconcile::apply(client, &changes).await {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_subcommand() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "rocketmq-exporter-rust",
+            "consumerProgress",
+            "-g",
+            "order_group",
+            "-n",
+            "127.0.0.1:9876",
+            "--json",
+        ]);
+        assert_eq!(cli.namesrv_addr.as_deref(), Some("127.0.0.1:9876"));
+        assert!(cli.json);
+        match cli.command {
+            Some(Command::ConsumerProgress { group, topic }) => {
+                assert_eq!(group, "order_group");
+                assert_eq!(topic, None);
+            }
+            command => panic!("unexpected command: {command:?}"),
+        }
+        assert!(Cli::parse_from(["rocketmq-exporter-rust"])
+            .command
+            .is_none());
+        assert_eq!(format_timestamp(0), "-");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_parse_replay() {
+        let cli = Cli::parse_from([
+            "rocketmq-exporter-rust",
+            "--replay",
+            "prod.jsonl",
+            "topicList",
+        ]);
+        assert_eq!(cli.replay, Some(PathBuf::from("prod.jsonl")));
+        assert!(Cli::try_parse_from([
+            "rocketmq-exporter-rust",
+            "--record",
+            "a.jsonl",
+            "--replay",
+            "b.jsonl"
+        ])
+        .is_err());
+    }
+}
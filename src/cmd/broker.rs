@@ -16,6 +16,12 @@ pub struct BrokerCommand {
     ext_fields: HashMap<String, String>,
 }
 
+impl Default for BrokerCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BrokerCommand {
     pub fn new() -> BrokerCommand {
         BrokerCommand {
@@ -30,23 +36,22 @@ impl BrokerCommand {
     }
 
     pub fn encode(&self) -> String {
-        let header = serde_json::to_string(&self).unwrap();
-        return header;
+        serde_json::to_string(&self).unwrap()
     }
 
-    pub fn into_bytes(&self) -> BytesMut {
+    pub fn to_bytes(&self) -> BytesMut {
         let mut buffer = BytesMut::new();
         let header_encode = self.encode();
         let length = header_encode.len();
         let frame_size = 4 + length;
 
         buffer.put_i32(frame_size as i32);
-        buffer.put_u8(0 as u8);
+        buffer.put_u8(0);
         buffer.put_u8(((length >> 16) & 0xFF) as u8);
         buffer.put_u8(((length >> 8) & 0xFF) as u8);
         buffer.put_u8((length & 0xFF) as u8);
         buffer.put(Bytes::from(header_encode.into_bytes()));
 
-        return buffer;
+        buffer
     }
 }
@@ -2,9 +2,11 @@ use std::{
     collections::HashMap,
     fmt::Display,
     io::{Cursor, Read},
+    sync::atomic::{AtomicI32, Ordering},
 };
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+pub use rocketmq_derive::CustomHeader;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio_util::codec;
@@ -15,11 +17,16 @@ use tokio_util::codec;
 #[derive(Debug)]
 pub struct RemotingCommand {
     header: Header,
-    body: String,
+    body: Bytes,
 }
 
 impl RemotingCommand {
-    pub fn parse(content: &BytesMut) -> RemotingCommand {
+    ///
+    /// 解析去掉了总长度的帧，帧的长度不够或者头部不是合法的JSON的时候返回None
+    pub fn parse(content: &BytesMut) -> Option<RemotingCommand> {
+        if content.len() < 4 {
+            return None;
+        }
         let mut buf = Cursor::new(&content[..]);
         let length = content.len() as i32;
 
@@ -27,33 +34,34 @@ impl RemotingCommand {
         let header_length = origin_header_length & 0xFFFFFF;
 
         let mut header_data = vec![0u8; header_length as usize];
-        buf.read(&mut header_data).unwrap();
+        buf.read_exact(&mut header_data).ok()?;
         let body_length = length - 4 - header_length;
         let mut body_data = vec![0u8; body_length as usize];
-        buf.read(&mut body_data).unwrap();
-        return RemotingCommand {
-            header: Header::parse(String::from_utf8(header_data).unwrap()),
-            body: String::from_utf8(body_data).unwrap(),
-        };
+        buf.read_exact(&mut body_data).ok()?;
+        Some(RemotingCommand {
+            header: Header::parse(String::from_utf8(header_data).ok()?)?,
+            body: Bytes::from(body_data),
+        })
     }
 
     pub fn encode_no_length(&self) -> BytesMut {
         let mut buffer = BytesMut::new();
         let header = self.header.encode();
         let length = header.len();
-        buffer.put_u8(0 as u8);
+        buffer.put_u8(0);
         buffer.put_u8(((length >> 16) & 0xFF) as u8);
         buffer.put_u8(((length >> 8) & 0xFF) as u8);
         buffer.put_u8((length & 0xFF) as u8);
         buffer.put(Bytes::from(header.into_bytes()));
+        buffer.put(self.body.clone());
 
-        return buffer;
+        buffer
     }
 
     pub fn new(code: RequestCode) -> RemotingCommand {
         RemotingCommand {
             header: Header::new(code),
-            body: String::from(""),
+            body: Bytes::new(),
         }
     }
 
@@ -65,15 +73,90 @@ impl RemotingCommand {
             header.ext_fields = encode_header;
         }
         RemotingCommand {
-            header: header,
-            body: String::from(""),
+            header,
+            body: Bytes::new(),
+        }
+    }
+
+    ///
+    /// 构造响应，opaque需要和请求的一致，flag的第0位表示这是一个响应
+    pub fn response(code: i32, opaque: i32) -> RemotingCommand {
+        let mut header = Header::new(RequestCode::GetBrokerClusterInfo);
+        header.code = code;
+        header.flag = RESPONSE_FLAG;
+        header.opaque = opaque;
+        RemotingCommand {
+            header,
+            body: Bytes::new(),
         }
     }
 
+    ///
+    /// 设置响应的extFields，例如GetMaxOffset的响应中的offset
+    pub fn with_ext_fields(mut self, ext_fields: HashMap<String, String>) -> RemotingCommand {
+        self.header.ext_fields = ext_fields;
+        self
+    }
+
+    ///
+    /// 请求的RequestCode或者响应的ResponseCode
+    pub fn code(&self) -> i32 {
+        self.header.code
+    }
+
+    pub fn opaque(&self) -> i32 {
+        self.header.opaque
+    }
+
+    ///
+    /// 头部中的flag、language、version以及remark等字段
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    ///
+    /// 设置响应的remark，Broker在错误的响应中说明原因
+    pub fn with_remark(mut self, remark: Option<String>) -> RemotingCommand {
+        self.header.remark = remark;
+        self
+    }
+
+    ///
+    /// 设置发送方的版本，例如: Broker 4.9.2的版本是397
+    pub fn with_version(mut self, version: i32) -> RemotingCommand {
+        self.header.version = version;
+        self
+    }
+
+    ///
+    /// 设置请求的body，例如创建订阅组的时候使用JSON格式的订阅组配置
+    pub fn with_body(mut self, body: Bytes) -> RemotingCommand {
+        self.body = body;
+        self
+    }
+
+    ///
+    /// JSON格式的body，body不是UTF-8的时候返回空字符串
     pub fn body(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap_or_default()
+    }
+
+    ///
+    /// 二进制格式的body，例如查询消息返回的消息存储格式
+    pub fn body_bytes(&self) -> &Bytes {
         &self.body
     }
 
+    pub fn ext_fields(&self) -> &HashMap<String, String> {
+        &self.header.ext_fields
+    }
+
+    ///
+    /// 把extFields解码为头部，一些响应的结果在extFields中而不是body中，例如: GetMaxOffset
+    pub fn decode_custom_header<T: CustomHeader>(&self) -> Result<T, HeaderError> {
+        T::decode(&self.header.ext_fields)
+    }
+
     pub fn is_success(&self) -> bool {
         let response_code = ResponseCode::Success;
         self.header.code == response_code.code()
@@ -86,6 +169,10 @@ impl Display for RemotingCommand {
     }
 }
 
+///
+/// 请求的opaque，每个请求递增，用来匹配请求和响应
+static REQUEST_ID: AtomicI32 = AtomicI32::new(0);
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Header {
     code: i32,
@@ -95,6 +182,8 @@ pub struct Header {
     #[serde(rename = "serializeTypeCurrentRPC")]
     serialize_type_current_rpc: String,
     version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remark: Option<String>,
     #[serde(rename = "extFields", default)]
     ext_fields: HashMap<String, String>,
 }
@@ -105,16 +194,16 @@ impl Header {
             code: request_code.code(),
             flag: 0,
             language: LanguageCode::RUST(String::from("RUST")),
-            opaque: 1,
+            opaque: REQUEST_ID.fetch_add(1, Ordering::Relaxed),
             serialize_type_current_rpc: String::from(""),
             version: 317,
+            remark: None,
             ext_fields: HashMap::new(),
         }
     }
 
-    pub fn parse(json_data: String) -> Header {
-        let h: Header = serde_json::from_str(&json_data).unwrap();
-        return h;
+    pub fn parse(json_data: String) -> Option<Header> {
+        serde_json::from_str(&json_data).ok()
     }
 
     pub fn code(&self) -> i32 {
@@ -137,9 +226,16 @@ impl Header {
         self.version
     }
 
+    pub fn language(&self) -> &LanguageCode {
+        &self.language
+    }
+
+    pub fn remark(&self) -> Option<&str> {
+        self.remark.as_deref()
+    }
+
     pub fn encode(&self) -> String {
-        let header_json = serde_json::to_string(&self).unwrap();
-        header_json
+        serde_json::to_string(&self).unwrap()
     }
 }
 
@@ -161,6 +257,7 @@ impl Display for Header {
 /// 枚举RocketMQ的Admin需要的一些类型信息
 ///
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum LanguageCode {
     JAVA(String),
     GO(String),
@@ -212,6 +309,10 @@ impl Display for LanguageCode {
     }
 }
 
+///
+/// RemotingCommand的flag中表示响应的位
+const RESPONSE_FLAG: i32 = 1;
+
 ///
 /// RocketMQ的RequestCode
 ///
@@ -459,6 +560,9 @@ impl ResponseCode {
     }
 }
 
+///
+/// 带长度前缀的RemotingCommand帧的编解码
+#[derive(Debug, Default)]
 pub struct CommandCoderc {}
 
 impl CommandCoderc {
@@ -493,8 +597,14 @@ impl codec::Decoder for CommandCoderc {
             return Ok(None);
         }
 
-        let response = RemotingCommand::parse(&src);
-        Ok(Some(response))
+        let mut frame = src.split_to(frame_len);
+        frame.advance(Self::PROTOCOL_LENGTH);
+        RemotingCommand::parse(&frame).map(Some).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Invalid remoting command frame",
+            )
+        })
     }
 }
 
@@ -503,84 +613,628 @@ impl codec::Encoder<RemotingCommand> for CommandCoderc {
     fn encode(&mut self, item: RemotingCommand, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let header = item.header.encode();
         let length = header.len();
-        let frame_size = 4 + length;
+        let frame_size = 4 + length + item.body.len();
 
         dst.put_i32(frame_size as i32);
-        dst.put_u8(0 as u8);
+        dst.put_u8(0);
         dst.put_u8(((length >> 16) & 0xFF) as u8);
         dst.put_u8(((length >> 8) & 0xFF) as u8);
         dst.put_u8((length & 0xFF) as u8);
         dst.put(Bytes::from(header.into_bytes()));
+        dst.put(item.body);
         Ok(())
     }
 }
 
 ///
-/// 实现请求的Header的特殊化的Trait
+/// 实现请求的Header的特殊化的Trait，一般通过`#[derive(CustomHeader)]`实现，
+/// 字段名转换为驼峰作为extFields的key，`#[header(rename = "...")]`指定其他的key，`Option`类型的字段是可选的，
+/// 依赖被重命名的时候通过`#[header(crate = "...")]`指定库的路径
 ///
 pub trait CustomHeader {
     ///
     /// 提供RocketMQ请求头部的编码trait
     fn encode(&self) -> HashMap<String, String>;
+
+    ///
+    /// 从响应的extFields解码头部
+    fn decode(ext_fields: &HashMap<String, String>) -> Result<Self, HeaderError>
+    where
+        Self: Sized;
 }
 
-const TOPIC: &str = "topic";
 ///
-/// 获取RocketMQ的Topic路由信息的头部
-pub struct TopicRouteInfoRequestHeader {
-    topic: String,
+/// 解码头部的时候必需的字段不存在，或者字段的值不能解析为字段的类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    MissingField(&'static str),
+    InvalidField { name: &'static str, value: String },
 }
 
-impl CustomHeader for TopicRouteInfoRequestHeader {
-    fn encode(&self) -> HashMap<String, String> {
-        let mut data = HashMap::new();
-        data.insert(TOPIC.to_string(), self.topic.clone());
-        data
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderError::MissingField(name) => write!(f, "missing header field: {name}"),
+            HeaderError::InvalidField { name, value } => {
+                write!(f, "invalid header field: {name}={value}")
+            }
+        }
     }
 }
 
+impl std::error::Error for HeaderError {}
+
+///
+/// 获取RocketMQ的Topic路由信息的头部
+#[derive(CustomHeader)]
+pub struct TopicRouteInfoRequestHeader {
+    topic: String,
+}
+
 impl TopicRouteInfoRequestHeader {
     pub fn new(topic: String) -> TopicRouteInfoRequestHeader {
-        TopicRouteInfoRequestHeader { topic: topic }
+        TopicRouteInfoRequestHeader { topic }
     }
 }
 
 ///
 /// 获取Topic的状态的头部
+#[derive(CustomHeader)]
 pub struct GetTopicStatsInfoHeader {
     topic: String,
 }
 
-impl CustomHeader for GetTopicStatsInfoHeader {
-    fn encode(&self) -> HashMap<String, String> {
-        let mut data = HashMap::new();
-        data.insert(TOPIC.to_string(), self.topic.clone());
-        data
-    }
-}
-
 impl GetTopicStatsInfoHeader {
     pub fn new(topic: String) -> GetTopicStatsInfoHeader {
-        GetTopicStatsInfoHeader { topic: topic }
+        GetTopicStatsInfoHeader { topic }
     }
 }
 
 ///
 /// 查找Topic被哪些消费者消费了
+#[derive(CustomHeader)]
 pub struct TopicConsumerByWhoHeader {
     topic: String,
 }
 
-impl CustomHeader for TopicConsumerByWhoHeader {
-    fn encode(&self) -> HashMap<String, String> {
-        let mut data = HashMap::new();
-        data.insert(TOPIC.to_string(), self.topic.clone());
-        data
+impl TopicConsumerByWhoHeader {
+    pub fn new(topic: String) -> TopicConsumerByWhoHeader {
+        TopicConsumerByWhoHeader { topic }
     }
 }
 
-impl TopicConsumerByWhoHeader {
-    pub fn new(topic: String) -> TopicConsumerByWhoHeader {
-        TopicConsumerByWhoHeader { topic: topic }
+///
+/// 获取消费者组的在线客户端连接的头部
+#[derive(CustomHeader)]
+pub struct GetConsumerConnectionListHeader {
+    consumer_group: String,
+}
+
+impl GetConsumerConnectionListHeader {
+    pub fn new(consumer_group: String) -> GetConsumerConnectionListHeader {
+        GetConsumerConnectionListHeader { consumer_group }
+    }
+}
+
+///
+/// 通过Broker获取消费者客户端运行时信息的头部
+#[derive(CustomHeader)]
+pub struct GetConsumerRunningInfoHeader {
+    consumer_group: String,
+    client_id: String,
+    jstack_enable: bool,
+}
+
+impl GetConsumerRunningInfoHeader {
+    pub fn new(
+        consumer_group: String,
+        client_id: String,
+        jstack_enable: bool,
+    ) -> GetConsumerRunningInfoHeader {
+        GetConsumerRunningInfoHeader {
+            consumer_group,
+            client_id,
+            jstack_enable,
+        }
+    }
+}
+
+///
+/// 获取消费者组在Topic上每个队列的消费进度的头部
+#[derive(CustomHeader)]
+pub struct GetConsumeStatsHeader {
+    consumer_group: String,
+    topic: String,
+}
+
+impl GetConsumeStatsHeader {
+    pub fn new(consumer_group: String, topic: String) -> GetConsumeStatsHeader {
+        GetConsumeStatsHeader {
+            consumer_group,
+            topic,
+        }
+    }
+}
+
+///
+/// 查询消费者组在每个队列上的消费时间跨度的头部
+#[derive(CustomHeader)]
+pub struct QueryConsumeTimeSpanHeader {
+    topic: String,
+    group: String,
+}
+
+impl QueryConsumeTimeSpanHeader {
+    pub fn new(topic: String, group: String) -> QueryConsumeTimeSpanHeader {
+        QueryConsumeTimeSpanHeader { topic, group }
+    }
+}
+
+///
+/// 获取队列中最早的消息的存储时间的头部
+#[derive(CustomHeader)]
+pub struct GetEarliestMsgStoretimeHeader {
+    topic: String,
+    queue_id: i32,
+}
+
+impl GetEarliestMsgStoretimeHeader {
+    pub fn new(topic: String, queue_id: i32) -> GetEarliestMsgStoretimeHeader {
+        GetEarliestMsgStoretimeHeader { topic, queue_id }
+    }
+}
+
+///
+/// 根据CommitLog的物理offset查询消息的头部
+#[derive(CustomHeader)]
+pub struct ViewMessageRequestHeader {
+    offset: i64,
+}
+
+impl ViewMessageRequestHeader {
+    pub fn new(offset: i64) -> ViewMessageRequestHeader {
+        ViewMessageRequestHeader { offset }
+    }
+}
+
+///
+/// 根据消息的Key在时间范围内查询消息的头部，时间单位是毫秒
+#[derive(CustomHeader)]
+pub struct QueryMessageRequestHeader {
+    topic: String,
+    key: String,
+    max_num: i32,
+    begin_timestamp: i64,
+    end_timestamp: i64,
+}
+
+impl QueryMessageRequestHeader {
+    pub fn new(
+        topic: String,
+        key: String,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> QueryMessageRequestHeader {
+        QueryMessageRequestHeader {
+            topic,
+            key,
+            max_num,
+            begin_timestamp,
+            end_timestamp,
+        }
+    }
+}
+
+///
+/// 查询队列中存储时间在指定时间之后的第一条消息的offset的头部，时间单位是毫秒
+#[derive(CustomHeader)]
+pub struct SearchOffsetRequestHeader {
+    topic: String,
+    queue_id: i32,
+    timestamp: i64,
+}
+
+impl SearchOffsetRequestHeader {
+    pub fn new(topic: String, queue_id: i32, timestamp: i64) -> SearchOffsetRequestHeader {
+        SearchOffsetRequestHeader {
+            topic,
+            queue_id,
+            timestamp,
+        }
+    }
+}
+
+///
+/// 获取队列的最大offset的头部
+#[derive(CustomHeader)]
+pub struct GetMaxOffsetRequestHeader {
+    topic: String,
+    queue_id: i32,
+}
+
+impl GetMaxOffsetRequestHeader {
+    pub fn new(topic: String, queue_id: i32) -> GetMaxOffsetRequestHeader {
+        GetMaxOffsetRequestHeader { topic, queue_id }
+    }
+}
+
+///
+/// 获取队列的最小offset的头部
+#[derive(CustomHeader)]
+pub struct GetMinOffsetRequestHeader {
+    topic: String,
+    queue_id: i32,
+}
+
+impl GetMinOffsetRequestHeader {
+    pub fn new(topic: String, queue_id: i32) -> GetMinOffsetRequestHeader {
+        GetMinOffsetRequestHeader { topic, queue_id }
+    }
+}
+
+///
+/// 查询消费者组在队列上提交的消费位点的头部
+#[derive(CustomHeader)]
+pub struct QueryConsumerOffsetRequestHeader {
+    consumer_group: String,
+    topic: String,
+    queue_id: i32,
+}
+
+impl QueryConsumerOffsetRequestHeader {
+    pub fn new(
+        consumer_group: String,
+        topic: String,
+        queue_id: i32,
+    ) -> QueryConsumerOffsetRequestHeader {
+        QueryConsumerOffsetRequestHeader {
+            consumer_group,
+            topic,
+            queue_id,
+        }
+    }
+}
+
+///
+/// GetMaxOffset、GetMinOffset、SearchOffsetByTimestamp和QueryConsumerOffset的响应头部
+#[derive(CustomHeader, Debug, PartialEq, Eq)]
+pub struct OffsetResponseHeader {
+    offset: i64,
+}
+
+impl OffsetResponseHeader {
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+///
+/// GetEarliestMsgStoretime的响应头部，时间单位是毫秒
+#[derive(CustomHeader, Debug, PartialEq, Eq)]
+pub struct GetEarliestMsgStoretimeResponseHeader {
+    timestamp: i64,
+}
+
+impl GetEarliestMsgStoretimeResponseHeader {
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+///
+/// 通过Broker重置消费者组在Topic上的消费位点的头部。
+/// queue_id和offset为空的时候按照timestamp重置所有的队列，否则把指定的队列重置到offset
+#[cfg(feature = "admin")]
+#[derive(CustomHeader)]
+pub struct ResetOffsetRequestHeader {
+    topic: String,
+    group: String,
+    timestamp: i64,
+    is_force: bool,
+    queue_id: Option<i32>,
+    offset: Option<i64>,
+}
+
+#[cfg(feature = "admin")]
+impl ResetOffsetRequestHeader {
+    pub fn by_timestamp(
+        topic: String,
+        group: String,
+        timestamp: i64,
+        is_force: bool,
+    ) -> ResetOffsetRequestHeader {
+        ResetOffsetRequestHeader {
+            topic,
+            group,
+            timestamp,
+            is_force,
+            queue_id: None,
+            offset: None,
+        }
+    }
+
+    pub fn by_offset(
+        topic: String,
+        group: String,
+        queue_id: i32,
+        offset: i64,
+    ) -> ResetOffsetRequestHeader {
+        ResetOffsetRequestHeader {
+            topic,
+            group,
+            timestamp: -1,
+            is_force: true,
+            queue_id: Some(queue_id),
+            offset: Some(offset),
+        }
+    }
+}
+
+///
+/// 创建Topic的时候使用的模板Topic
+#[cfg(feature = "admin")]
+const AUTO_CREATE_TOPIC_KEY_TOPIC: &str = "TBW102";
+
+///
+/// 在Broker上创建或者更新Topic的头部
+#[cfg(feature = "admin")]
+#[derive(CustomHeader)]
+pub struct CreateTopicRequestHeader {
+    topic: String,
+    default_topic: String,
+    read_queue_nums: i32,
+    write_queue_nums: i32,
+    perm: i32,
+    topic_filter_type: String,
+    topic_sys_flag: i32,
+    order: bool,
+}
+
+#[cfg(feature = "admin")]
+impl CreateTopicRequestHeader {
+    pub fn new(
+        topic: String,
+        read_queue_nums: i32,
+        write_queue_nums: i32,
+        perm: i32,
+        topic_filter_type: String,
+        topic_sys_flag: i32,
+        order: bool,
+    ) -> CreateTopicRequestHeader {
+        CreateTopicRequestHeader {
+            topic,
+            default_topic: AUTO_CREATE_TOPIC_KEY_TOPIC.to_string(),
+            read_queue_nums,
+            write_queue_nums,
+            perm,
+            topic_filter_type,
+            topic_sys_flag,
+            order,
+        }
+    }
+}
+
+///
+/// 在Broker上删除Topic的头部
+#[cfg(feature = "admin")]
+#[derive(CustomHeader)]
+pub struct DeleteTopicRequestHeader {
+    topic: String,
+}
+
+#[cfg(feature = "admin")]
+impl DeleteTopicRequestHeader {
+    pub fn new(topic: String) -> DeleteTopicRequestHeader {
+        DeleteTopicRequestHeader { topic }
+    }
+}
+
+///
+/// 在Nameserver上删除Topic的头部，只删除cluster_name集群中的Broker上的路由
+#[cfg(feature = "admin")]
+#[derive(CustomHeader)]
+pub struct DeleteTopicFromNamesrvRequestHeader {
+    topic: String,
+    cluster_name: String,
+}
+
+#[cfg(feature = "admin")]
+impl DeleteTopicFromNamesrvRequestHeader {
+    pub fn new(topic: String, cluster_name: String) -> DeleteTopicFromNamesrvRequestHeader {
+        DeleteTopicFromNamesrvRequestHeader {
+            topic,
+            cluster_name,
+        }
+    }
+}
+
+///
+/// 在Broker上删除订阅组的头部，clean_offset为true的时候同时删除订阅组的消费进度
+#[cfg(feature = "admin")]
+#[derive(CustomHeader)]
+pub struct DeleteSubscriptionGroupRequestHeader {
+    group_name: String,
+    clean_offset: bool,
+}
+
+#[cfg(feature = "admin")]
+impl DeleteSubscriptionGroupRequestHeader {
+    pub fn new(group_name: String, clean_offset: bool) -> DeleteSubscriptionGroupRequestHeader {
+        DeleteSubscriptionGroupRequestHeader {
+            group_name,
+            clean_offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_command_with_body() {
+        let command = RemotingCommand::new(RequestCode::UpdateAndCreateSubscriptionGroup)
+            .with_body(Bytes::from_static(b"{\"groupName\":\"g\"}"));
+        let data = command.encode_no_length();
+        let header_length = data[1..4]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        assert_eq!(&data[4 + header_length..], b"{\"groupName\":\"g\"}");
+
+        let parsed = RemotingCommand::parse(&data).unwrap();
+        assert_eq!(parsed.body(), "{\"groupName\":\"g\"}");
+    }
+
+    #[test]
+    fn test_parse_invalid_frame() {
+        assert!(RemotingCommand::parse(&BytesMut::from(&[0u8, 0][..])).is_none());
+        assert!(RemotingCommand::parse(&BytesMut::from(&[0u8, 0, 0, 9, b'{'][..])).is_none());
+        assert!(RemotingCommand::parse(&BytesMut::from(&b"\0\0\0\x02{}"[..])).is_none());
+
+        let first = RemotingCommand::new(RequestCode::GetBrokerClusterInfo);
+        let second = RemotingCommand::new(RequestCode::GetBrokerClusterInfo);
+        assert_ne!(first.opaque(), second.opaque());
+    }
+
+    #[derive(CustomHeader, Debug, PartialEq)]
+    struct TestHeader {
+        consumer_group: String,
+        queue_id: i32,
+        #[header(rename = "isForce")]
+        force: bool,
+        offset: Option<i64>,
+    }
+
+    #[derive(CustomHeader)]
+    #[header(crate = "crate")]
+    struct CratePathHeader {
+        topic: String,
+    }
+
+    #[test]
+    fn test_derive_custom_header_crate_path() {
+        let header = CratePathHeader {
+            topic: "order_topic".to_string(),
+        };
+        let fields = header.encode();
+        assert_eq!(fields["topic"], "order_topic");
+        assert_eq!(
+            CratePathHeader::decode(&fields).unwrap().topic,
+            "order_topic"
+        );
+    }
+
+    #[test]
+    fn test_derive_custom_header() {
+        let header = TestHeader {
+            consumer_group: "order_group".to_string(),
+            queue_id: 3,
+            force: true,
+            offset: None,
+        };
+        let fields = header.encode();
+        assert_eq!(
+            fields,
+            HashMap::from([
+                ("consumerGroup".to_string(), "order_group".to_string()),
+                ("queueId".to_string(), "3".to_string()),
+                ("isForce".to_string(), "true".to_string()),
+            ])
+        );
+        assert_eq!(TestHeader::decode(&fields), Ok(header));
+
+        let mut fields = fields;
+        fields.insert("offset".to_string(), "100".to_string());
+        assert_eq!(TestHeader::decode(&fields).unwrap().offset, Some(100));
+        fields.insert("queueId".to_string(), "x".to_string());
+        assert_eq!(
+            TestHeader::decode(&fields),
+            Err(HeaderError::InvalidField {
+                name: "queueId",
+                value: "x".to_string()
+            })
+        );
+        fields.remove("consumerGroup");
+        assert_eq!(
+            TestHeader::decode(&fields),
+            Err(HeaderError::MissingField("consumerGroup"))
+        );
+    }
+
+    #[test]
+    fn test_offset_request_headers() {
+        let fields = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            SearchOffsetRequestHeader::new("order_topic".to_string(), 2, 1706162310000).encode(),
+            fields(&[
+                ("topic", "order_topic"),
+                ("queueId", "2"),
+                ("timestamp", "1706162310000")
+            ])
+        );
+        assert_eq!(
+            GetMaxOffsetRequestHeader::new("order_topic".to_string(), 2).encode(),
+            fields(&[("topic", "order_topic"), ("queueId", "2")])
+        );
+        assert_eq!(
+            GetMinOffsetRequestHeader::new("order_topic".to_string(), 2).encode(),
+            fields(&[("topic", "order_topic"), ("queueId", "2")])
+        );
+    }
+
+    #[test]
+    fn test_command_codec() {
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let mut codec = CommandCoderc::default();
+        let mut buf = BytesMut::new();
+        let request = RemotingCommand::build(
+            RequestCode::GetTOpicStatsInfo,
+            Some(GetTopicStatsInfoHeader::new("order_topic".to_string())),
+        );
+        codec.encode(request, &mut buf).unwrap();
+        codec
+            .encode(
+                RemotingCommand::response(0, 7).with_body(Bytes::from_static(b"{}")),
+                &mut buf,
+            )
+            .unwrap();
+        let partial = buf.len() - 1;
+        let mut partial_buf = buf.split_to(partial);
+        let request = codec.decode(&mut partial_buf).unwrap().unwrap();
+        assert_eq!(request.code(), 202);
+        assert_eq!(request.ext_fields()["topic"], "order_topic");
+        assert!(codec.decode(&mut partial_buf).unwrap().is_none());
+
+        partial_buf.unsplit(buf);
+        let response = codec.decode(&mut partial_buf).unwrap().unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.opaque(), 7);
+        assert_eq!(response.body(), "{}");
+        assert!(partial_buf.is_empty());
+    }
+
+    #[test]
+    fn test_decode_response_custom_header() {
+        let header = r#"{"code":0,"extFields":{"offset":"1024"},"flag":1,"language":"JAVA","opaque":3,"serializeTypeCurrentRPC":"JSON","version":397}"#;
+        let mut data = BytesMut::new();
+        data.put_i32(header.len() as i32);
+        data.put(header.as_bytes());
+        let response = RemotingCommand::parse(&data).unwrap();
+        let header: OffsetResponseHeader = response.decode_custom_header().unwrap();
+        assert_eq!(header.offset(), 1024);
+        assert_eq!(
+            response
+                .decode_custom_header::<GetEarliestMsgStoretimeResponseHeader>()
+                .unwrap_err()
+                .to_string(),
+            "missing header field: timestamp"
+        );
     }
 }
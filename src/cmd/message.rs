@@ -0,0 +1,541 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::Read,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use bytes::{Buf, Bytes};
+use flate2::{read::ZlibDecoder, Crc};
+
+///
+/// 消息存储格式的魔数，Topic的长度使用1个字节存储
+const MESSAGE_MAGIC_CODE: i32 = -626843481;
+///
+/// 第二版消息存储格式的魔数，Topic的长度使用2个字节存储
+const MESSAGE_MAGIC_CODE_V2: i32 = -626843477;
+///
+/// sysflag中表示body被压缩的标志位
+const COMPRESSED_FLAG: i32 = 1;
+///
+/// sysflag中的压缩类型，0是4.x版本的zlib
+const COMPRESSION_TYPE_COMPARATOR: i32 = 0x7 << 8;
+const COMPRESSION_ZLIB_TYPE: i32 = 0x3 << 8;
+///
+/// sysflag中表示产生消息的主机是IPv6的标志位
+const BORNHOST_V6_FLAG: i32 = 1 << 4;
+///
+/// sysflag中表示存储消息的主机是IPv6的标志位
+const STOREHOST_V6_FLAG: i32 = 1 << 5;
+
+///
+/// 消息属性的名字和值之间的分隔符
+const NAME_VALUE_SEPARATOR: char = '\u{1}';
+///
+/// 消息属性之间的分隔符
+const PROPERTY_SEPARATOR: char = '\u{2}';
+
+pub const PROPERTY_KEYS: &str = "KEYS";
+pub const PROPERTY_TAGS: &str = "TAGS";
+pub const PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX: &str = "UNIQ_KEY";
+
+///
+/// Broker生成的offset消息ID，包含存储消息的Broker地址以及消息在CommitLog中的物理offset。
+/// IPv4的消息ID是32个十六进制字符，IPv6的消息ID是56个十六进制字符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageId {
+    addr: SocketAddr,
+    offset: i64,
+}
+
+impl MessageId {
+    pub fn new(addr: SocketAddr, offset: i64) -> MessageId {
+        MessageId { addr, offset }
+    }
+
+    pub fn parse(msg_id: &str) -> Option<MessageId> {
+        if msg_id.len() % 2 != 0 || !msg_id.is_ascii() {
+            return None;
+        }
+        let data: Vec<u8> = (0..msg_id.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&msg_id[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let mut data = Bytes::from(data);
+        let ip_length = match data.len() {
+            16 => 4,
+            28 => 16,
+            _ => return None,
+        };
+        let addr = read_host(&mut data, ip_length == 16)?;
+        let offset = data.get_i64();
+        Some(MessageId { addr, offset })
+    }
+
+    pub fn addr(&self) -> &SocketAddr {
+        &self.addr
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+impl Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ip = match self.addr.ip() {
+            IpAddr::V4(ip) => ip.octets().to_vec(),
+            IpAddr::V6(ip) => ip.octets().to_vec(),
+        };
+        for b in ip
+            .iter()
+            .chain((self.addr.port() as i32).to_be_bytes().iter())
+            .chain(self.offset.to_be_bytes().iter())
+        {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Broker存储的消息，对应Java客户端的MessageExt
+#[derive(Debug, Clone)]
+pub struct MessageExt {
+    topic: String,
+    store_size: i32,
+    body_crc: i32,
+    body_crc_valid: bool,
+    queue_id: i32,
+    flag: i32,
+    queue_offset: i64,
+    commit_log_offset: i64,
+    sys_flag: i32,
+    born_timestamp: i64,
+    born_host: SocketAddr,
+    store_timestamp: i64,
+    store_host: SocketAddr,
+    reconsume_times: i32,
+    prepared_transaction_offset: i64,
+    body: Bytes,
+    properties: HashMap<String, String>,
+}
+
+impl MessageExt {
+    ///
+    /// 从buf中解码一条消息，数据不完整或者魔数不对的时候返回None。
+    /// zlib压缩的body会被解压，其他压缩类型的body原样保留
+    pub fn decode(buf: &mut Bytes) -> Option<MessageExt> {
+        MessageExt::decode_frame(split_frame(buf)?)
+    }
+
+    ///
+    /// 解码连续存放的多条消息，例如按照Key查询消息返回的body。
+    /// 长度完整但是无法解码的消息按照store_size跳过，结尾不完整的数据被忽略
+    pub fn decode_batch(mut data: Bytes) -> Vec<MessageExt> {
+        let mut messages = vec![];
+        while let Some(frame) = split_frame(&mut data) {
+            messages.extend(MessageExt::decode_frame(frame));
+        }
+        messages
+    }
+
+    fn decode_frame(mut data: Bytes) -> Option<MessageExt> {
+        let total_size = data.get_i32();
+
+        let magic_code = read_i32(&mut data)?;
+        if magic_code != MESSAGE_MAGIC_CODE && magic_code != MESSAGE_MAGIC_CODE_V2 {
+            return None;
+        }
+        let body_crc = read_i32(&mut data)?;
+        let queue_id = read_i32(&mut data)?;
+        let flag = read_i32(&mut data)?;
+        let queue_offset = read_i64(&mut data)?;
+        let commit_log_offset = read_i64(&mut data)?;
+        let sys_flag = read_i32(&mut data)?;
+        let born_timestamp = read_i64(&mut data)?;
+        let born_host = read_host(&mut data, sys_flag & BORNHOST_V6_FLAG != 0)?;
+        let store_timestamp = read_i64(&mut data)?;
+        let store_host = read_host(&mut data, sys_flag & STOREHOST_V6_FLAG != 0)?;
+        let reconsume_times = read_i32(&mut data)?;
+        let prepared_transaction_offset = read_i64(&mut data)?;
+
+        let body_length = read_i32(&mut data)?;
+        let body = take(&mut data, body_length.max(0) as usize)?;
+        let body_crc_valid = crc32(&body) == body_crc;
+        let body = decompress_body(body, sys_flag)?;
+
+        let topic_length = if magic_code == MESSAGE_MAGIC_CODE_V2 {
+            take(&mut data, 2)?.get_i16().max(0) as usize
+        } else {
+            take(&mut data, 1)?.get_u8() as usize
+        };
+        let topic = String::from_utf8(take(&mut data, topic_length)?.to_vec()).ok()?;
+
+        let properties_length = take(&mut data, 2)?.get_i16();
+        let properties = take(&mut data, properties_length.max(0) as usize)?;
+        let properties = decode_properties(&String::from_utf8_lossy(&properties));
+
+        Some(MessageExt {
+            topic,
+            store_size: total_size,
+            body_crc,
+            body_crc_valid,
+            queue_id,
+            flag,
+            queue_offset,
+            commit_log_offset,
+            sys_flag,
+            born_timestamp,
+            born_host,
+            store_timestamp,
+            store_host,
+            reconsume_times,
+            prepared_transaction_offset,
+            body,
+            properties,
+        })
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    ///
+    /// 消息在CommitLog中占用的字节数
+    pub fn store_size(&self) -> i32 {
+        self.store_size
+    }
+
+    ///
+    /// Broker存储的body的CRC32，压缩消息是压缩后的body的CRC32
+    pub fn body_crc(&self) -> i32 {
+        self.body_crc
+    }
+
+    ///
+    /// 存储的body和body_crc是否一致，不一致说明CommitLog中的消息已经损坏
+    pub fn is_body_crc_valid(&self) -> bool {
+        self.body_crc_valid
+    }
+
+    pub fn queue_id(&self) -> i32 {
+        self.queue_id
+    }
+
+    pub fn flag(&self) -> i32 {
+        self.flag
+    }
+
+    pub fn queue_offset(&self) -> i64 {
+        self.queue_offset
+    }
+
+    pub fn commit_log_offset(&self) -> i64 {
+        self.commit_log_offset
+    }
+
+    pub fn sys_flag(&self) -> i32 {
+        self.sys_flag
+    }
+
+    pub fn born_timestamp(&self) -> i64 {
+        self.born_timestamp
+    }
+
+    pub fn born_host(&self) -> &SocketAddr {
+        &self.born_host
+    }
+
+    pub fn store_timestamp(&self) -> i64 {
+        self.store_timestamp
+    }
+
+    pub fn store_host(&self) -> &SocketAddr {
+        &self.store_host
+    }
+
+    pub fn reconsume_times(&self) -> i32 {
+        self.reconsume_times
+    }
+
+    pub fn prepared_transaction_offset(&self) -> i64 {
+        self.prepared_transaction_offset
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub fn properties(&self) -> &HashMap<String, String> {
+        &self.properties
+    }
+
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(|v| v.as_str())
+    }
+
+    pub fn tags(&self) -> Option<&str> {
+        self.property(PROPERTY_TAGS)
+    }
+
+    ///
+    /// 消息的Key，多个Key之间使用空格分隔
+    pub fn keys(&self) -> Vec<&str> {
+        self.property(PROPERTY_KEYS)
+            .map(|keys| keys.split(' ').filter(|k| !k.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    ///
+    /// 客户端生成的唯一消息ID，也就是发送消息返回的msgId
+    pub fn unique_key(&self) -> Option<&str> {
+        self.property(PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX)
+    }
+
+    ///
+    /// Broker生成的offset消息ID
+    pub fn offset_msg_id(&self) -> MessageId {
+        MessageId::new(self.store_host, self.commit_log_offset)
+    }
+}
+
+///
+/// 从buf中切出一条完整的消息，长度不完整的时候返回None
+fn split_frame(buf: &mut Bytes) -> Option<Bytes> {
+    if buf.remaining() < 4 {
+        return None;
+    }
+    let total_size = (&buf[..4]).get_i32();
+    if total_size < 4 || buf.remaining() < total_size as usize {
+        return None;
+    }
+    Some(buf.split_to(total_size as usize))
+}
+
+///
+/// 和Broker的UtilAll.crc32一致，去掉了最高位
+fn crc32(data: &[u8]) -> i32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    (crc.sum() & 0x7FFFFFFF) as i32
+}
+
+///
+/// 解压body，没有压缩或者不是zlib压缩的时候原样返回
+fn decompress_body(body: Bytes, sys_flag: i32) -> Option<Bytes> {
+    let compression_type = sys_flag & COMPRESSION_TYPE_COMPARATOR;
+    if sys_flag & COMPRESSED_FLAG == 0
+        || (compression_type != 0 && compression_type != COMPRESSION_ZLIB_TYPE)
+    {
+        return Some(body);
+    }
+    let mut decompressed = vec![];
+    ZlibDecoder::new(body.as_ref())
+        .read_to_end(&mut decompressed)
+        .ok()?;
+    Some(Bytes::from(decompressed))
+}
+
+///
+/// 解析消息属性，格式是: name 0x01 value 0x02 name 0x01 value 0x02
+fn decode_properties(source: &str) -> HashMap<String, String> {
+    source
+        .split(PROPERTY_SEPARATOR)
+        .filter_map(|item| item.split_once(NAME_VALUE_SEPARATOR))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn take(data: &mut Bytes, length: usize) -> Option<Bytes> {
+    if data.remaining() < length {
+        return None;
+    }
+    Some(data.split_to(length))
+}
+
+fn read_i32(data: &mut Bytes) -> Option<i32> {
+    Some(take(data, 4)?.get_i32())
+}
+
+fn read_i64(data: &mut Bytes) -> Option<i64> {
+    Some(take(data, 8)?.get_i64())
+}
+
+///
+/// 读取IP地址和端口，端口使用4个字节存储
+fn read_host(data: &mut Bytes, v6: bool) -> Option<SocketAddr> {
+    let ip = if v6 {
+        let mut octets = [0u8; 16];
+        take(data, 16)?.copy_to_slice(&mut octets);
+        IpAddr::V6(Ipv6Addr::from(octets))
+    } else {
+        let mut octets = [0u8; 4];
+        take(data, 4)?.copy_to_slice(&mut octets);
+        IpAddr::V4(Ipv4Addr::from(octets))
+    };
+    let port = read_i32(data)?;
+    Some(SocketAddr::new(ip, port as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::{BufMut, BytesMut};
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    fn encode_message(topic: &str, body: &[u8], properties: &str, offset: i64) -> BytesMut {
+        encode_message_with(MESSAGE_MAGIC_CODE, 0, topic, body, properties, offset)
+    }
+
+    fn encode_message_with(
+        magic_code: i32,
+        sys_flag: i32,
+        topic: &str,
+        body: &[u8],
+        properties: &str,
+        offset: i64,
+    ) -> BytesMut {
+        let born_host: &[u8] = if sys_flag & BORNHOST_V6_FLAG != 0 {
+            &[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        } else {
+            &[10, 20, 141, 1]
+        };
+        let store_host: &[u8] = if sys_flag & STOREHOST_V6_FLAG != 0 {
+            &[
+                0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x49,
+            ]
+        } else {
+            &[10, 20, 141, 73]
+        };
+        let mut data = BytesMut::new();
+        data.put_i32(magic_code);
+        data.put_i32(crc32(body));
+        data.put_i32(3);
+        data.put_i32(0);
+        data.put_i64(42);
+        data.put_i64(offset);
+        data.put_i32(sys_flag);
+        data.put_i64(1706162310000);
+        data.put_slice(born_host);
+        data.put_i32(52000);
+        data.put_i64(1706162310005);
+        data.put_slice(store_host);
+        data.put_i32(20911);
+        data.put_i32(2);
+        data.put_i64(0);
+        data.put_i32(body.len() as i32);
+        data.put_slice(body);
+        if magic_code == MESSAGE_MAGIC_CODE_V2 {
+            data.put_i16(topic.len() as i16);
+        } else {
+            data.put_u8(topic.len() as u8);
+        }
+        data.put_slice(topic.as_bytes());
+        data.put_i16(properties.len() as i16);
+        data.put_slice(properties.as_bytes());
+
+        let mut message = BytesMut::new();
+        message.put_i32(data.len() as i32 + 4);
+        message.put(data);
+        message
+    }
+
+    #[test]
+    fn test_message_id() {
+        let msg_id = "0A148D4900004FAF000000000000A0B1";
+        let id = MessageId::parse(msg_id).unwrap();
+        assert_eq!(id.addr().to_string(), "10.20.141.73:20399");
+        assert_eq!(id.offset(), 0xA0B1);
+        assert_eq!(id.to_string(), msg_id);
+        assert!(MessageId::parse("0A148D49").is_none());
+        assert!(MessageId::parse("ZZ148D4900004FAF000000000000A0B1").is_none());
+    }
+
+    #[test]
+    fn test_decode_messages() {
+        let properties =
+            "KEYS\u{1}order-1 order-2\u{2}TAGS\u{1}created\u{2}UNIQ_KEY\u{1}7F000001\u{2}";
+        let mut data = encode_message("order_topic", b"hello", properties, 1024);
+        data.put(encode_message("order_topic", b"world", "", 2048));
+        let messages = MessageExt::decode_batch(data.freeze());
+        assert_eq!(messages.len(), 2);
+
+        let message = &messages[0];
+        assert_eq!(message.topic(), "order_topic");
+        assert_eq!(message.queue_id(), 3);
+        assert_eq!(message.queue_offset(), 42);
+        assert_eq!(message.body().as_ref(), b"hello");
+        assert_eq!(message.keys(), vec!["order-1", "order-2"]);
+        assert_eq!(message.tags(), Some("created"));
+        assert_eq!(message.unique_key(), Some("7F000001"));
+        assert_eq!(message.born_host().to_string(), "10.20.141.1:52000");
+        assert_eq!(message.store_host().to_string(), "10.20.141.73:20911");
+        assert_eq!(message.reconsume_times(), 2);
+        assert_eq!(
+            message.offset_msg_id().to_string(),
+            "0A148D49000051AF0000000000000400"
+        );
+        assert_eq!(messages[1].commit_log_offset(), 2048);
+        assert!(messages[1].properties().is_empty());
+        assert!(messages.iter().all(|m| m.is_body_crc_valid()));
+    }
+
+    #[test]
+    fn test_decode_corrupted_messages() {
+        // body被修改以后CRC不一致
+        let mut data = encode_message("order_topic", b"hello", "", 1024);
+        let body_at = data.len() - b"hello".len() - 1 - "order_topic".len() - 2;
+        data[body_at] = b'j';
+        // 压缩标志位对应的body不是zlib数据，解压失败的消息被跳过
+        data.put(encode_message_with(
+            MESSAGE_MAGIC_CODE,
+            COMPRESSED_FLAG,
+            "order_topic",
+            b"not zlib",
+            "",
+            2048,
+        ));
+        data.put(encode_message("order_topic", b"world", "", 3072));
+        let messages = MessageExt::decode_batch(data.freeze());
+        assert_eq!(messages.len(), 2);
+
+        assert_eq!(messages[0].body().as_ref(), b"jello");
+        assert!(!messages[0].is_body_crc_valid());
+        assert_eq!(messages[1].commit_log_offset(), 3072);
+        assert!(messages[1].is_body_crc_valid());
+    }
+
+    #[test]
+    fn test_decode_compressed_ipv6_message() {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"compressed body").unwrap();
+        let body = encoder.finish().unwrap();
+        let sys_flag = COMPRESSED_FLAG | BORNHOST_V6_FLAG | STOREHOST_V6_FLAG;
+        let mut data = encode_message_with(
+            MESSAGE_MAGIC_CODE_V2,
+            sys_flag,
+            "order_topic",
+            &body,
+            "TAGS\u{1}paid",
+            4096,
+        );
+        // 结尾不完整的消息不会被解码
+        data.put_slice(&[0, 0, 1, 0]);
+        let messages = MessageExt::decode_batch(data.freeze());
+        assert_eq!(messages.len(), 1);
+
+        let message = &messages[0];
+        assert_eq!(message.body().as_ref(), b"compressed body");
+        assert_eq!(message.topic(), "order_topic");
+        assert_eq!(message.tags(), Some("paid"));
+        assert_eq!(message.born_host().to_string(), "[fe80::1]:52000");
+        assert_eq!(message.store_host().to_string(), "[2001:db8::49]:20911");
+        let id = MessageId::parse(&message.offset_msg_id().to_string()).unwrap();
+        assert_eq!(id.addr(), message.store_host());
+        assert_eq!(id.offset(), 4096);
+    }
+}
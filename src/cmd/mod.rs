@@ -1,2 +1,6 @@
+//!
+//! Remoting协议的请求和响应：RemotingCommand的编解码、请求码、请求头部以及消息的解码
+
 pub mod broker;
 pub mod command;
+pub mod message;
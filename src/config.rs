@@ -0,0 +1,172 @@
+use std::{collections::HashMap, env};
+
+use rocketmq_exporter_rust::util::filter::{split_patterns, NameFilter};
+
+///
+/// Nameserver的地址
+const NAMESRV_ADDR: &str = "ROCKETMQ_NAMESRV_ADDR";
+///
+/// Exporter的HTTP监听地址
+const LISTEN_ADDR: &str = "EXPORTER_LISTEN_ADDR";
+///
+/// 需要以info指标导出的配置项，逗号分隔
+const CONFIG_KEYS: &str = "EXPORTER_CONFIG_KEYS";
+///
+/// 是否通过GetAllDelayOffset获取Broker持久化的延迟消息投递进度
+const DELAY_OFFSET_ENABLED: &str = "EXPORTER_DELAY_OFFSET_ENABLED";
+///
+/// 是否通过Broker获取消费者客户端的运行时信息
+const CONSUMER_RUNNING_INFO_ENABLED: &str = "EXPORTER_CONSUMER_RUNNING_INFO_ENABLED";
+///
+/// 获取消费者客户端运行时信息的时候是否同时获取线程栈
+const CONSUMER_JSTACK_ENABLED: &str = "EXPORTER_CONSUMER_JSTACK_ENABLED";
+///
+/// 是否通过QueryConsumeTimeSpan采集消费者组在每个队列上的消费延迟
+const CONSUMER_DELAY_ENABLED: &str = "EXPORTER_CONSUMER_DELAY_ENABLED";
+///
+/// 是否跳过系统Topic，不采集系统Topic的队列和消费者组
+const EXCLUDE_SYSTEM_TOPICS: &str = "EXPORTER_EXCLUDE_SYSTEM_TOPICS";
+///
+/// 需要采集的Topic的正则表达式，逗号分隔
+const TOPIC_INCLUDE: &str = "EXPORTER_TOPIC_INCLUDE";
+///
+/// 不需要采集的Topic的正则表达式，逗号分隔
+const TOPIC_EXCLUDE: &str = "EXPORTER_TOPIC_EXCLUDE";
+///
+/// 需要采集的消费者组的正则表达式，逗号分隔
+const GROUP_INCLUDE: &str = "EXPORTER_GROUP_INCLUDE";
+///
+/// 不需要采集的消费者组的正则表达式，逗号分隔
+const GROUP_EXCLUDE: &str = "EXPORTER_GROUP_EXCLUDE";
+///
+/// 每个指标的最大序列数，0表示不限制
+const SERIES_LIMIT: &str = "EXPORTER_SERIES_LIMIT";
+///
+/// 单独设置某些指标的最大序列数，格式是name=limit，逗号分隔
+const SERIES_LIMITS: &str = "EXPORTER_SERIES_LIMITS";
+
+///
+/// /api/snapshot的快照缓存的秒数，0表示每次请求都重新生成
+const SNAPSHOT_CACHE_SECONDS: &str = "EXPORTER_SNAPSHOT_CACHE_SECONDS";
+
+///
+/// Exporter的配置，从环境变量读取
+#[derive(Debug, Clone)]
+pub struct ExporterConfig {
+    pub namesrv_addr: String,
+    pub listen_addr: String,
+    pub config_keys: Vec<String>,
+    pub delay_offset_enabled: bool,
+    pub consumer_running_info_enabled: bool,
+    pub consumer_jstack_enabled: bool,
+    pub consumer_delay_enabled: bool,
+    pub exclude_system_topics: bool,
+    pub topic_filter: NameFilter,
+    pub group_filter: NameFilter,
+    pub series_limit: usize,
+    pub series_limits: HashMap<String, usize>,
+    pub snapshot_cache_seconds: u64,
+}
+
+impl Default for ExporterConfig {
+    fn default() -> Self {
+        ExporterConfig {
+            namesrv_addr: String::from("rocketmq-cloud.cpaas-test:9876"),
+            listen_addr: String::from("0.0.0.0:5557"),
+            config_keys: vec![],
+            delay_offset_enabled: false,
+            consumer_running_info_enabled: false,
+            consumer_jstack_enabled: false,
+            consumer_delay_enabled: true,
+            exclude_system_topics: false,
+            topic_filter: NameFilter::default(),
+            group_filter: NameFilter::default(),
+            series_limit: 0,
+            series_limits: HashMap::new(),
+            snapshot_cache_seconds: 30,
+        }
+    }
+}
+
+impl ExporterConfig {
+    pub fn from_env() -> ExporterConfig {
+        let default = ExporterConfig::default();
+        ExporterConfig {
+            namesrv_addr: env::var(NAMESRV_ADDR).unwrap_or(default.namesrv_addr),
+            listen_addr: env::var(LISTEN_ADDR).unwrap_or(default.listen_addr),
+            config_keys: Self::env_list(CONFIG_KEYS),
+            delay_offset_enabled: Self::env_bool(DELAY_OFFSET_ENABLED)
+                .unwrap_or(default.delay_offset_enabled),
+            consumer_running_info_enabled: Self::env_bool(CONSUMER_RUNNING_INFO_ENABLED)
+                .unwrap_or(default.consumer_running_info_enabled),
+            consumer_jstack_enabled: Self::env_bool(CONSUMER_JSTACK_ENABLED)
+                .unwrap_or(default.consumer_jstack_enabled),
+            consumer_delay_enabled: Self::env_bool(CONSUMER_DELAY_ENABLED)
+                .unwrap_or(default.consumer_delay_enabled),
+            exclude_system_topics: Self::env_bool(EXCLUDE_SYSTEM_TOPICS)
+                .unwrap_or(default.exclude_system_topics),
+            topic_filter: Self::env_filter(TOPIC_INCLUDE, TOPIC_EXCLUDE),
+            group_filter: Self::env_filter(GROUP_INCLUDE, GROUP_EXCLUDE),
+            series_limit: env::var(SERIES_LIMIT)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default.series_limit),
+            series_limits: Self::env_list(SERIES_LIMITS)
+                .iter()
+                .filter_map(|item| {
+                    let (name, limit) = item.split_once('=')?;
+                    Some((name.trim().to_string(), limit.trim().parse().ok()?))
+                })
+                .collect(),
+            snapshot_cache_seconds: env::var(SNAPSHOT_CACHE_SECONDS)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default.snapshot_cache_seconds),
+        }
+    }
+
+    ///
+    /// 指标的最大序列数，没有单独设置的时候使用全局的限制，0表示不限制
+    pub fn series_limit_of(&self, family: &str) -> usize {
+        self.series_limits
+            .get(family)
+            .copied()
+            .unwrap_or(self.series_limit)
+    }
+
+    ///
+    /// 读取include和exclude正则表达式列表，正则表达式不合法的时候直接退出
+    fn env_filter(include: &str, exclude: &str) -> NameFilter {
+        let patterns = |name: &str| {
+            env::var(name)
+                .map(|v| split_patterns(&v))
+                .unwrap_or_default()
+        };
+        NameFilter::new(&patterns(include), &patterns(exclude))
+            .unwrap_or_else(|e| panic!("{} or {} is not a valid regex: {}", include, exclude, e))
+    }
+
+    ///
+    /// 读取布尔类型的环境变量，支持true/false/1/0
+    fn env_bool(name: &str) -> Option<bool> {
+        match env::var(name).ok()?.trim().to_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 读取逗号分隔的环境变量列表，忽略空项
+    fn env_list(name: &str) -> Vec<String> {
+        match env::var(name) {
+            Ok(value) => value
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+}
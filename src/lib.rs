@@ -0,0 +1,48 @@
+//!
+//! RocketMQ的Remoting协议和管理接口的客户端，Exporter和命令行都是基于这个库实现的。
+//!
+//! [`Client`]连接Nameserver，按需连接Broker，把管理请求编码为[`RemotingCommand`]发送，
+//! 并把响应的body解析为[`remoting::response`]中的类型：
+//! ```no_run
+//! use rocketmq_exporter_rust::Client;
+//!
+//! # async fn run() {
+//! let mut client = Client::connection("127.0.0.1:9876").await.unwrap();
+//! let broker_info = client.broker_info().await.unwrap();
+//! for addr in broker_info.all_broker_addrs() {
+//!     if let Some(info) = client.broker_runtime_info(addr).await {
+//!         println!("{addr}: {:?}", info.entries().get("brokerVersionDesc"));
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! 没有封装的请求可以通过`#[derive(CustomHeader)]`定义头部，通过[`RemotingCommand::build`]构造：
+//! ```
+//! use rocketmq_exporter_rust::{CustomHeader, RemotingCommand, RequestCode};
+//!
+//! #[derive(CustomHeader)]
+//! struct GetKvConfigRequestHeader {
+//!     namespace: String,
+//!     key: String,
+//! }
+//!
+//! let header = GetKvConfigRequestHeader {
+//!     namespace: "ORDER_TOPIC_CONFIG".to_string(),
+//!     key: "order_topic".to_string(),
+//! };
+//! assert_eq!(header.encode()["namespace"], "ORDER_TOPIC_CONFIG");
+//! let command = RemotingCommand::build(RequestCode::GetKvConfig, Some(header));
+//! ```
+//! 结果在extFields中的响应通过[`RemotingCommand::decode_custom_header`]解码为同样派生了`CustomHeader`的类型，
+//! 缺少必需的字段或者字段不能解析的时候返回[`cmd::command::HeaderError`]。
+//! 修改集群状态的操作在`admin` feature中，默认不编译。
+
+extern crate self as rocketmq_exporter_rust;
+
+pub mod cmd;
+pub mod remoting;
+pub mod util;
+
+pub use cmd::command::{CustomHeader, RemotingCommand, RequestCode};
+pub use remoting::client::Client;
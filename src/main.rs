@@ -1,45 +1,85 @@
-use remoting::client::Client;
+use std::time::Duration;
 
-mod cmd;
-mod remoting;
-mod util;
+use clap::Parser;
+use cli::Cli;
+use config::ExporterConfig;
+use metrics::collector::Collector;
+#[cfg(feature = "mock")]
+use rocketmq_exporter_rust::remoting::{recording::Recording, replay::Replay};
+use rocketmq_exporter_rust::{remoting::recording::Recorder, Client};
+
+mod cli;
+mod config;
+mod metrics;
+#[cfg(feature = "admin")]
+mod reconcile;
 
 #[tokio::main]
 pub async fn main() {
-    println!("Start rocketmq exporter...");
-    rocketmq_metrics().await;
-}
-
-async fn rocketmq_metrics() {
-    let addr = String::from("rocketmq-cloud.cpaas-test:9876");
-    let mut client = Client::connection(addr).await.unwrap();
-    let response = client.broker_info().await;
-    println!("broker information:{:?}", response);
-
-    let response = client.topic_list().await;
-    println!("Topic List:{:?}", response);
-
-    let topics = response.topics();
-    for topic in topics {
-        let route_reponse = client.topic_route(topic.clone()).await;
-        if let Some(response) = route_reponse {
-            println!("Topic:{topic},response:{response:?}");
-        }
+    let cli = Cli::parse();
+    let mut config = ExporterConfig::from_env();
+    if let Some(namesrv_addr) = cli.namesrv_addr {
+        config.namesrv_addr = namesrv_addr;
     }
-
-    for topic in topics {
-        let response = client.topic_stats(topic.clone()).await;
-        if let Some(data) = response {
-            println!("Topic:{topic} stats:{data:?}");
+    // 回放的时候连接本地的模拟集群，需要一直持有直到退出
+    #[cfg(feature = "mock")]
+    let _replay = match &cli.replay {
+        Some(path) => {
+            let recording = Recording::load(path).unwrap_or_else(|e| {
+                eprintln!("failed to load recording {}: {e}", path.display());
+                std::process::exit(2);
+            });
+            let replay = Replay::start(&recording).await.unwrap_or_else(|e| {
+                eprintln!("failed to replay {}: {e}", path.display());
+                std::process::exit(1);
+            });
+            config.namesrv_addr = replay.namesrv_addr().to_string();
+            Some(replay)
+        }
+        None => None,
+    };
+    let recorder = cli.record.as_ref().map(|path| {
+        Recorder::create(path).unwrap_or_else(|e| {
+            eprintln!("failed to create recording {}: {e}", path.display());
+            std::process::exit(2);
+        })
+    });
+    match cli.command {
+        Some(command) => {
+            let mut client = connect(&config, recorder).await;
+            std::process::exit(cli::run(&mut client, command, cli.json).await);
+        }
+        None => {
+            println!("Start rocketmq exporter...");
+            rocketmq_metrics(config, recorder).await;
         }
     }
+}
 
-    for topic in topics {
-        let result = client.query_topic_consume_by_who(topic.clone()).await;
-        if let Some(data) = result {
-            println!("Topic:{topic} consumer-groups:{data:?}");
+///
+/// 连接Nameserver，连接失败的时候打印一行错误并且退出
+async fn connect(config: &ExporterConfig, recorder: Option<Recorder>) -> Client {
+    let addr = config.namesrv_addr.clone();
+    let result = match recorder {
+        Some(recorder) => Client::connection_with_recorder(addr.as_str(), recorder).await,
+        None => Client::connection(addr.as_str()).await,
+    };
+    match result {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("failed to connect to nameserver {addr}: {e}");
+            std::process::exit(1);
         }
     }
+}
 
-    client.query_broker_runtime_info().await;
+async fn rocketmq_metrics(config: ExporterConfig, recorder: Option<Recorder>) {
+    let client = connect(&config, recorder).await;
+    let listen_addr = config.listen_addr.clone();
+    let snapshot_ttl = Duration::from_secs(config.snapshot_cache_seconds);
+    let collector = Collector::new(client, config);
+    println!("Serve metrics on http://{listen_addr}/metrics");
+    metrics::server::serve(&listen_addr, collector, snapshot_ttl)
+        .await
+        .unwrap();
 }
@@ -0,0 +1,1173 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use rocketmq_exporter_rust::{
+    remoting::{
+        client::Client,
+        response::{
+            BrokerInformation, BrokerRuntimeInfo, BrokerRuntimeInfoTable, ClientConnection,
+            ConfigProperties, ConsumeTimeSpans, ConsumerConnection, ConsumerRunningInfo,
+            DelayOffsets, TopicStats, WindowTps, PROP_CLIENT_VERSION, PROP_CONSUMEORDERLY,
+        },
+    },
+    util::topic::{consumer_group_of, TopicType},
+};
+
+use crate::config::ExporterConfig;
+
+use super::{
+    family::{Aggregation, MetricFamily},
+    histogram::LatencyHistogram,
+    snapshot::{Snapshot, TopicSnapshot},
+};
+
+///
+/// 每个Broker都不一样的配置项，计算配置指纹的时候需要排除
+const BROKER_IDENTITY_KEYS: [&str; 4] = ["brokerName", "brokerId", "brokerIP1", "brokerIP2"];
+
+///
+/// 超过基数限制的时候依次去掉的标签：先合并到Topic级别，再合并所有的客户端
+const AGGREGATE_LABELS: [&str; 2] = ["queue_id", "client_id"];
+
+///
+/// 从RocketMQ采集指标，保存需要跨多次采集的状态
+pub struct Collector {
+    client: Client,
+    config: ExporterConfig,
+    config_fingerprints: HashMap<String, u64>,
+    config_changes: HashMap<String, u64>,
+    put_latency: HashMap<String, LatencyHistogram>,
+}
+
+impl Collector {
+    pub fn new(client: Client, config: ExporterConfig) -> Collector {
+        Collector {
+            client,
+            config,
+            config_fingerprints: HashMap::new(),
+            config_changes: HashMap::new(),
+            put_latency: HashMap::new(),
+        }
+    }
+
+    ///
+    /// 执行一次完整的采集
+    pub async fn collect(&mut self) -> Vec<MetricFamily> {
+        let mut families = vec![];
+        let broker_info = match self.client.broker_info().await {
+            Some(broker_info) => broker_info,
+            None => return families,
+        };
+        let groups = self.collect_topics(&broker_info, &mut families).await;
+        if self.config.consumer_running_info_enabled {
+            self.collect_consumer_running_info(&groups, &mut families)
+                .await;
+        }
+        self.collect_broker_config(&broker_info, &mut families)
+            .await;
+        self.collect_broker_runtime(&broker_info, &mut families)
+            .await;
+        self.collect_namesrv_config(&mut families).await;
+        self.limit_cardinality(&mut families);
+        families
+    }
+
+    ///
+    /// 获取整个集群的快照：每个Master Broker的运行时信息，以及每个Topic的路由、队列offset和消费者组。
+    /// Topic和消费者组按照和指标相同的配置过滤，无法从Nameserver获取集群信息或者Topic列表的时候返回None
+    pub async fn snapshot(&mut self) -> Option<Snapshot> {
+        let broker_info = self.client.broker_info().await?;
+        let mut broker_runtime = BTreeMap::new();
+        for addr in broker_info.all_broker_addrs() {
+            if let Some(info) = self.client.broker_runtime_info(addr).await {
+                broker_runtime.insert(addr.clone(), info);
+            }
+        }
+        let system_topics = self.system_topics(&broker_info).await;
+        let topic_list = self.client.topic_list().await?;
+        let mut topics = BTreeMap::new();
+        for topic in topic_list.topics() {
+            let topic_type = TopicType::classify(topic, &system_topics);
+            if topic_type == TopicType::System && self.config.exclude_system_topics {
+                continue;
+            }
+            if !self.topic_selected(topic) {
+                continue;
+            }
+            let route = match self.client.topic_route(topic.clone()).await {
+                Some(route) => route,
+                None => continue,
+            };
+            let mut stats: Option<TopicStats> = None;
+            let mut consumer_groups = BTreeSet::new();
+            for broker in route.brokers() {
+                let addr = match broker.master_broker_addrs() {
+                    Some(addr) => addr.clone(),
+                    None => continue,
+                };
+                if let Some(broker_stats) = self.client.topic_stats(&addr, topic.clone()).await {
+                    match stats.as_mut() {
+                        Some(stats) => stats.merge(broker_stats),
+                        None => stats = Some(broker_stats),
+                    }
+                }
+                if let Some(groups) = self
+                    .client
+                    .query_topic_consume_by_who(&addr, topic.clone())
+                    .await
+                {
+                    consumer_groups.extend(
+                        groups
+                            .groups()
+                            .iter()
+                            .filter(|g| self.config.group_filter.matches(g))
+                            .cloned(),
+                    );
+                }
+            }
+            topics.insert(
+                topic.clone(),
+                TopicSnapshot {
+                    topic_type: topic_type.as_str(),
+                    route,
+                    stats,
+                    consumer_groups,
+                },
+            );
+        }
+        Some(Snapshot {
+            timestamp: chrono::Local::now().timestamp_millis(),
+            broker_info,
+            broker_runtime,
+            topics,
+        })
+    }
+
+    ///
+    /// 限制每个指标的序列数，超过限制的时候合并或者丢弃序列，并导出被合并和丢弃的序列数
+    fn limit_cardinality(&self, families: &mut Vec<MetricFamily>) {
+        let mut aggregated = MetricFamily::gauge(
+            "rocketmq_exporter_series_aggregated",
+            "Series merged into coarser series in the last scrape because the family exceeded its series limit.",
+        );
+        let mut dropped = MetricFamily::gauge(
+            "rocketmq_exporter_series_dropped",
+            "Series dropped in the last scrape because the family still exceeded its series limit after aggregation.",
+        );
+        for family in families.iter_mut() {
+            let limit = self.config.series_limit_of(family.name());
+            if limit == 0 {
+                continue;
+            }
+            let result = family.limit(limit, &AGGREGATE_LABELS);
+            let labels = [("family", family.name())];
+            aggregated.add(&labels, result.aggregated as f64);
+            dropped.add(&labels, result.dropped as f64);
+        }
+        families.push(aggregated);
+        families.push(dropped);
+    }
+
+    ///
+    /// 遍历所有的Topic，采集每个队列的offset、Topic的消费者组以及消费延迟，返回消费者组以及可以查询消费者组的Broker地址。
+    /// Topic和消费者组在发送请求之前按照配置的正则表达式过滤，重试和死信Topic同时按照所属的消费者组过滤
+    async fn collect_topics(
+        &mut self,
+        broker_info: &BrokerInformation,
+        families: &mut Vec<MetricFamily>,
+    ) -> BTreeMap<String, String> {
+        let mut max_offset = MetricFamily::gauge(
+            "rocketmq_topic_queue_max_offset",
+            "Max offset of the message queue.",
+        );
+        let mut min_offset = MetricFamily::gauge(
+            "rocketmq_topic_queue_min_offset",
+            "Min offset of the message queue.",
+        );
+        let mut consumer_group = MetricFamily::gauge(
+            "rocketmq_topic_consumer_group_info",
+            "Consumer group subscribed to the topic.",
+        );
+
+        let mut delay = ConsumerDelayFamilies::new();
+        let mut retry_dlq = RetryDlqFamilies::new();
+
+        let mut all_groups = BTreeMap::new();
+        let system_topics = self.system_topics(broker_info).await;
+        let topics = self
+            .client
+            .topic_list()
+            .await
+            .map(|topics| topics.topics().clone())
+            .unwrap_or_default();
+        for topic in topics.iter() {
+            let topic_type = TopicType::classify(topic, &system_topics);
+            if topic_type == TopicType::System && self.config.exclude_system_topics {
+                continue;
+            }
+            if !self.topic_selected(topic) {
+                continue;
+            }
+            let route = match self.client.topic_route(topic.clone()).await {
+                Some(route) => route,
+                None => continue,
+            };
+
+            let mut groups = BTreeSet::new();
+            for broker in route.brokers() {
+                let addr = match broker.master_broker_addrs() {
+                    Some(addr) => addr.clone(),
+                    None => continue,
+                };
+                if let Some(stats) = self.client.topic_stats(&addr, topic.clone()).await {
+                    let mut queues: Vec<_> = stats.offset_table().iter().collect();
+                    queues.sort_by_key(|(mq, _)| mq.queue_id());
+                    for (mq, offset) in queues {
+                        let queue_id = mq.queue_id().to_string();
+                        let labels = [
+                            ("cluster", broker.cluster()),
+                            ("broker", mq.broker_name()),
+                            ("topic", mq.topic()),
+                            ("topic_type", topic_type.as_str()),
+                            ("queue_id", queue_id.as_str()),
+                        ];
+                        max_offset.add(&labels, offset.max_offset() as f64);
+                        min_offset.add(&labels, offset.min_offset() as f64);
+                    }
+                    retry_dlq.add_offsets(broker.cluster(), topic, &stats);
+                }
+                if let (TopicType::Retry, Some(group)) =
+                    (TopicType::of(topic), consumer_group_of(topic))
+                {
+                    if let Some(stats) = self
+                        .client
+                        .consume_stats(&addr, group.to_string(), topic.clone())
+                        .await
+                    {
+                        retry_dlq.add_retry_backlog(broker.cluster(), group, stats.backlog());
+                    }
+                }
+                if let Some(data) = self
+                    .client
+                    .query_topic_consume_by_who(&addr, topic.clone())
+                    .await
+                {
+                    let topic_groups: Vec<&String> = data
+                        .groups()
+                        .iter()
+                        .filter(|group| self.config.group_filter.matches(group))
+                        .collect();
+                    for group in topic_groups.iter() {
+                        all_groups
+                            .entry(group.to_string())
+                            .or_insert_with(|| addr.clone());
+                    }
+                    groups.extend(topic_groups.iter().map(|group| group.to_string()));
+                    if self.config.consumer_delay_enabled {
+                        for group in topic_groups {
+                            if let Some(spans) = self
+                                .client
+                                .query_consume_time_span(&addr, topic.clone(), group.clone())
+                                .await
+                            {
+                                delay.add(broker.cluster(), group, &spans);
+                            }
+                        }
+                    }
+                }
+            }
+            for group in groups.iter() {
+                consumer_group.add(
+                    &[
+                        ("topic", topic),
+                        ("topic_type", topic_type.as_str()),
+                        ("group", group),
+                    ],
+                    1.0,
+                );
+            }
+        }
+
+        families.push(max_offset);
+        families.push(min_offset);
+        families.push(consumer_group);
+        delay.push_to(families);
+        retry_dlq.push_to(families);
+        all_groups
+    }
+
+    ///
+    /// 重试Topic和死信Topic属于消费者组，只按照消费者组过滤；其他的Topic按照Topic名字过滤
+    fn topic_selected(&self, topic: &str) -> bool {
+        match consumer_group_of(topic) {
+            Some(group) => self.config.group_filter.matches(group),
+            None => self.config.topic_filter.matches(topic),
+        }
+    }
+
+    ///
+    /// 合并Nameserver和每个Master Broker返回的系统Topic列表
+    async fn system_topics(&mut self, broker_info: &BrokerInformation) -> HashSet<String> {
+        let mut system_topics = HashSet::new();
+        if let Some(topics) = self.client.system_topic_list().await {
+            system_topics.extend(topics.topics().iter().cloned());
+        }
+        for broker in broker_info.master_brokers() {
+            let addr = match broker.master_broker_addrs() {
+                Some(addr) => addr.clone(),
+                None => continue,
+            };
+            if let Some(topics) = self.client.system_topic_list_from_broker(&addr).await {
+                system_topics.extend(topics.topics().iter().cloned());
+            }
+        }
+        system_topics
+    }
+
+    ///
+    /// 通过Broker获取每个消费者客户端的运行时信息，用来排查消费慢的客户端
+    async fn collect_consumer_running_info(
+        &mut self,
+        groups: &BTreeMap<String, String>,
+        families: &mut Vec<MetricFamily>,
+    ) {
+        let mut clients = ConsumerClientFamilies::new();
+        for (group, addr) in groups.iter() {
+            let connection = match self.client.consumer_connection(addr, group.clone()).await {
+                Some(connection) => connection,
+                None => continue,
+            };
+            for client in connection.connections() {
+                let running_info = self
+                    .client
+                    .consumer_running_info(
+                        addr,
+                        group.clone(),
+                        client.client_id().to_string(),
+                        self.config.consumer_jstack_enabled,
+                    )
+                    .await;
+                if let Some(running_info) = running_info {
+                    clients.add(group, &connection, client, &running_info);
+                }
+            }
+        }
+        clients.push_to(families);
+    }
+
+    ///
+    /// 采集Broker的配置：白名单中的配置项作为info指标，配置指纹和变更次数用来发现Broker之间的配置漂移
+    async fn collect_broker_config(
+        &mut self,
+        broker_info: &BrokerInformation,
+        families: &mut Vec<MetricFamily>,
+    ) {
+        let mut info = MetricFamily::gauge(
+            "rocketmq_broker_config_info",
+            "Broker config value of the allowlisted keys.",
+        );
+        let mut fingerprint = MetricFamily::gauge(
+            "rocketmq_broker_config_fingerprint",
+            "Fingerprint of the broker config, brokers with different values have drifted.",
+        );
+        let mut changes = MetricFamily::counter(
+            "rocketmq_broker_config_changes_total",
+            "Number of times the broker config has changed since the exporter started.",
+        );
+
+        for broker in broker_info.master_brokers() {
+            let addr = match broker.master_broker_addrs() {
+                Some(addr) => addr.clone(),
+                None => continue,
+            };
+            let config = match self.client.broker_config(&addr).await {
+                Some(config) => config,
+                None => continue,
+            };
+            let cluster = broker.cluster();
+            let broker_name = broker.broker_name();
+
+            for key in self.config.config_keys.iter() {
+                if let Some(value) = config.get(key) {
+                    info.add(
+                        &[
+                            ("cluster", cluster),
+                            ("broker", broker_name),
+                            ("key", key),
+                            ("value", value),
+                        ],
+                        1.0,
+                    );
+                }
+            }
+
+            let current = config_fingerprint(&config);
+            let count = self.config_changes.entry(addr.clone()).or_insert(0);
+            if let Some(previous) = self.config_fingerprints.insert(addr, current) {
+                if previous != current {
+                    *count += 1;
+                }
+            }
+            let labels = [("cluster", cluster), ("broker", broker_name)];
+            fingerprint.add(&labels, current as f64);
+            changes.add(&labels, *count as f64);
+        }
+
+        families.push(info);
+        families.push(fingerprint);
+        families.push(changes);
+    }
+
+    ///
+    /// 采集Broker的运行时信息，每个Broker只请求一次GetBrokerRuntimeInfo
+    async fn collect_broker_runtime(
+        &mut self,
+        broker_info: &BrokerInformation,
+        families: &mut Vec<MetricFamily>,
+    ) {
+        let mut schedule = ScheduleMessageFamilies::new();
+        let mut disk = DiskFamilies::new();
+        let mut tps = TpsFamilies::new();
+        let mut put_latency = MetricFamily::histogram(
+            "rocketmq_broker_put_latency_seconds",
+            "Put message latency accumulated from the per-minute putMessageDistributeTime snapshots, the sum is estimated from the bucket midpoints.",
+        );
+
+        for broker in broker_info.master_brokers() {
+            let addr = match broker.master_broker_addrs() {
+                Some(addr) => addr.clone(),
+                None => continue,
+            };
+            let runtime_info = match self.client.broker_runtime_info(&addr).await {
+                Some(runtime_info) => runtime_info,
+                None => continue,
+            };
+            let delay_offsets = if self.config.delay_offset_enabled {
+                self.client.delay_offsets(&addr).await
+            } else {
+                None
+            };
+            let labels = [
+                ("cluster", broker.cluster()),
+                ("broker", broker.broker_name()),
+            ];
+            schedule.add(&labels, &runtime_info, delay_offsets.as_ref());
+            disk.add(&labels, &runtime_info);
+            tps.add(&labels, &runtime_info);
+
+            let histogram = self.put_latency.entry(addr).or_default();
+            histogram.observe(&runtime_info.table().put_message_distribute_time());
+            let buckets = histogram.buckets();
+            if !buckets.is_empty() {
+                put_latency.add_histogram(&labels, &buckets, histogram.sum(), histogram.count());
+            }
+        }
+
+        schedule.push_to(families);
+        disk.push_to(families);
+        tps.push_to(families);
+        families.push(put_latency);
+    }
+
+    ///
+    /// 采集Nameserver的配置，白名单中的配置项作为info指标
+    async fn collect_namesrv_config(&mut self, families: &mut Vec<MetricFamily>) {
+        let mut info = MetricFamily::gauge(
+            "rocketmq_namesrv_config_info",
+            "Nameserver config value of the allowlisted keys.",
+        );
+        if let Some(config) = self.client.namesrv_config().await {
+            let namesrv = self.client.namesrv_addr().to_string();
+            for key in self.config.config_keys.iter() {
+                if let Some(value) = config.get(key) {
+                    info.add(
+                        &[("namesrv", &namesrv), ("key", key), ("value", value)],
+                        1.0,
+                    );
+                }
+            }
+        }
+        families.push(info);
+    }
+}
+
+///
+/// 延迟消息每个延迟级别的投递进度以及积压
+struct ScheduleMessageFamilies {
+    delivered_offset: MetricFamily,
+    max_offset: MetricFamily,
+    backlog: MetricFamily,
+    persisted_offset: MetricFamily,
+}
+
+impl ScheduleMessageFamilies {
+    fn new() -> ScheduleMessageFamilies {
+        ScheduleMessageFamilies {
+            delivered_offset: MetricFamily::gauge(
+                "rocketmq_schedule_message_delivered_offset",
+                "Offset of the delay level queue that has been delivered to the real topic.",
+            ),
+            max_offset: MetricFamily::gauge(
+                "rocketmq_schedule_message_max_offset",
+                "Max offset of the delay level queue.",
+            ),
+            backlog: MetricFamily::gauge(
+                "rocketmq_schedule_message_backlog",
+                "Number of scheduled messages waiting to be delivered in the delay level.",
+            ),
+            persisted_offset: MetricFamily::gauge(
+                "rocketmq_schedule_message_persisted_offset",
+                "Delivered offset of the delay level persisted by the broker (delayOffset.json).",
+            ),
+        }
+    }
+
+    fn add(
+        &mut self,
+        labels: &[(&str, &str)],
+        runtime_info: &BrokerRuntimeInfo,
+        delay_offsets: Option<&DelayOffsets>,
+    ) {
+        for offset in runtime_info.table().schedule_message_offsets() {
+            let level = offset.delay_level().to_string();
+            let mut level_labels = labels.to_vec();
+            level_labels.push(("delay_level", &level));
+
+            self.delivered_offset
+                .add(&level_labels, offset.delivered_offset() as f64);
+            self.max_offset
+                .add(&level_labels, offset.max_offset() as f64);
+            self.backlog.add(&level_labels, offset.backlog() as f64);
+            if let Some(persisted) = delay_offsets.and_then(|d| d.offset(offset.delay_level())) {
+                self.persisted_offset.add(&level_labels, persisted as f64);
+            }
+        }
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        families.push(self.delivered_offset);
+        families.push(self.max_offset);
+        families.push(self.backlog);
+        families.push(self.persisted_offset);
+    }
+}
+
+///
+/// Broker存储目录的容量、磁盘使用率以及未刷盘的数据量
+struct DiskFamilies {
+    capacity: MetricFamily,
+    free: MetricFamily,
+    commit_log_ratio: MetricFamily,
+    consume_queue_ratio: MetricFamily,
+    path_ratio: MetricFamily,
+    remain_to_flush: MetricFamily,
+}
+
+impl DiskFamilies {
+    fn new() -> DiskFamilies {
+        DiskFamilies {
+            capacity: MetricFamily::gauge(
+                "rocketmq_broker_commitlog_dir_capacity_bytes",
+                "Total capacity of the disk where the commitlog directory is located.",
+            ),
+            free: MetricFamily::gauge(
+                "rocketmq_broker_commitlog_dir_free_bytes",
+                "Free space of the disk where the commitlog directory is located.",
+            ),
+            commit_log_ratio: MetricFamily::gauge(
+                "rocketmq_broker_commitlog_disk_ratio",
+                "Used ratio of the disk where the commitlog is stored.",
+            ),
+            consume_queue_ratio: MetricFamily::gauge(
+                "rocketmq_broker_consumequeue_disk_ratio",
+                "Used ratio of the disk where the consume queue is stored.",
+            ),
+            path_ratio: MetricFamily::gauge(
+                "rocketmq_broker_commitlog_path_disk_ratio",
+                "Used ratio of the disk for each commitlog store path.",
+            ),
+            remain_to_flush: MetricFamily::gauge(
+                "rocketmq_broker_remain_data_to_flush_bytes",
+                "Data that has been written to the commitlog but not yet flushed to disk.",
+            ),
+        }
+    }
+
+    fn add(&mut self, labels: &[(&str, &str)], runtime_info: &BrokerRuntimeInfo) {
+        let table = runtime_info.table();
+        if let Some(capacity) = table.commit_log_dir_capacity() {
+            self.capacity.add(labels, capacity.total_bytes() as f64);
+            self.free.add(labels, capacity.free_bytes() as f64);
+        }
+        if let Some(ratio) = table.commit_log_disk_ratio() {
+            self.commit_log_ratio.add(labels, ratio);
+        }
+        if let Some(ratio) = table.consume_queue_disk_ratio() {
+            self.consume_queue_ratio.add(labels, ratio);
+        }
+        for (path, ratio) in table.commit_log_path_disk_ratios() {
+            let mut path_labels = labels.to_vec();
+            path_labels.push(("path", &path));
+            self.path_ratio.add(&path_labels, ratio);
+        }
+        if let Some(bytes) = table.remain_how_many_data_to_flush() {
+            self.remain_to_flush.add(labels, bytes as f64);
+        }
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        families.push(self.capacity);
+        families.push(self.free);
+        families.push(self.commit_log_ratio);
+        families.push(self.consume_queue_ratio);
+        families.push(self.path_ratio);
+        families.push(self.remain_to_flush);
+    }
+}
+
+///
+/// 按照集群和消费者组汇总的重试Topic和死信Topic的消息数量
+#[derive(Default)]
+struct GroupMessages {
+    retry_written: i64,
+    retry_backlog: Option<i64>,
+    dlq_written: i64,
+    dlq_stored: i64,
+}
+
+///
+/// 消费者组的重试消息和死信消息，重试Topic和死信Topic在多个Broker上的队列按消费者组汇总
+struct RetryDlqFamilies {
+    groups: BTreeMap<(String, String), GroupMessages>,
+}
+
+impl RetryDlqFamilies {
+    fn new() -> RetryDlqFamilies {
+        RetryDlqFamilies {
+            groups: BTreeMap::new(),
+        }
+    }
+
+    fn group_mut(&mut self, cluster: &str, group: &str) -> &mut GroupMessages {
+        self.groups
+            .entry((cluster.to_string(), group.to_string()))
+            .or_default()
+    }
+
+    fn add_offsets(&mut self, cluster: &str, topic: &str, stats: &TopicStats) {
+        let group = match consumer_group_of(topic) {
+            Some(group) => group,
+            None => return,
+        };
+        let total: i64 = stats.offset_table().values().map(|o| o.max_offset()).sum();
+        let stored: i64 = stats
+            .offset_table()
+            .values()
+            .map(|o| o.max_offset() - o.min_offset())
+            .sum();
+        let topic_type = TopicType::of(topic);
+        let messages = self.group_mut(cluster, group);
+        match topic_type {
+            TopicType::Retry => messages.retry_written += total,
+            TopicType::Dlq => {
+                messages.dlq_written += total;
+                messages.dlq_stored += stored;
+            }
+            TopicType::Normal | TopicType::System => {}
+        }
+    }
+
+    fn add_retry_backlog(&mut self, cluster: &str, group: &str, backlog: i64) {
+        let messages = self.group_mut(cluster, group);
+        *messages.retry_backlog.get_or_insert(0) += backlog;
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        let mut retry_written = MetricFamily::gauge(
+            "rocketmq_consumer_retry_messages_written",
+            "Messages ever written to the retry topic of the consumer group, summed from the max offsets of the reachable brokers.",
+        );
+        let mut retry_backlog = MetricFamily::gauge(
+            "rocketmq_consumer_retry_backlog",
+            "Messages in the retry topic of the consumer group waiting to be consumed again.",
+        );
+        let mut dlq_written = MetricFamily::gauge(
+            "rocketmq_consumer_dlq_messages_written",
+            "Messages ever written to the dead letter topic of the consumer group, summed from the max offsets of the reachable brokers. It drops when a broker is unreachable, use delta() rather than rate() to alert on growth.",
+        );
+        let mut dlq_stored = MetricFamily::gauge(
+            "rocketmq_consumer_dlq_messages",
+            "Messages currently stored in the dead letter topic of the consumer group.",
+        );
+        for ((cluster, group), messages) in self.groups.iter() {
+            let labels = [("cluster", cluster.as_str()), ("group", group.as_str())];
+            retry_written.add(&labels, messages.retry_written as f64);
+            if let Some(backlog) = messages.retry_backlog {
+                retry_backlog.add(&labels, backlog as f64);
+            }
+            dlq_written.add(&labels, messages.dlq_written as f64);
+            dlq_stored.add(&labels, messages.dlq_stored as f64);
+        }
+        families.push(retry_written);
+        families.push(retry_backlog);
+        families.push(dlq_written);
+        families.push(dlq_stored);
+    }
+}
+
+///
+/// 消费者组在每个队列上的消费延迟，通过QueryConsumeTimeSpan由Broker根据消息的存储时间计算
+struct ConsumerDelayFamilies {
+    delay: MetricFamily,
+    consume_timestamp: MetricFamily,
+}
+
+impl ConsumerDelayFamilies {
+    fn new() -> ConsumerDelayFamilies {
+        ConsumerDelayFamilies {
+            delay: MetricFamily::gauge(
+                "rocketmq_consumer_delay_seconds",
+                "Time since the oldest unconsumed message of the queue was stored, 0 when there is no backlog.",
+            ).with_aggregation(Aggregation::Max),
+            consume_timestamp: MetricFamily::gauge(
+                "rocketmq_consumer_consume_timestamp_seconds",
+                "Store time of the last message consumed by the group in the queue.",
+            ).with_aggregation(Aggregation::Max),
+        }
+    }
+
+    fn add(&mut self, cluster: &str, group: &str, spans: &ConsumeTimeSpans) {
+        let mut spans: Vec<_> = spans.time_spans().iter().collect();
+        spans.sort_by_key(|span| span.message_queue().queue_id());
+        for span in spans {
+            let mq = span.message_queue();
+            let queue_id = mq.queue_id().to_string();
+            let labels = [
+                ("cluster", cluster),
+                ("broker", mq.broker_name()),
+                ("topic", mq.topic()),
+                ("queue_id", queue_id.as_str()),
+                ("group", group),
+            ];
+            self.delay
+                .add(&labels, span.delay_time().max(0) as f64 / 1000.0);
+            if span.consume_time_stamp() > 0 {
+                self.consume_timestamp
+                    .add(&labels, span.consume_time_stamp() as f64 / 1000.0);
+            }
+        }
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        families.push(self.delay);
+        families.push(self.consume_timestamp);
+    }
+}
+
+///
+/// 消费者客户端的运行时信息，包括每个队列的缓存和锁状态、每个Topic的拉取和消费统计以及线程状态
+struct ConsumerClientFamilies {
+    info: MetricFamily,
+    subscription: MetricFamily,
+    cached_messages: MetricFamily,
+    cached_bytes: MetricFamily,
+    locked: MetricFamily,
+    last_pull: MetricFamily,
+    last_consume: MetricFamily,
+    pull_rt: MetricFamily,
+    pull_tps: MetricFamily,
+    consume_rt: MetricFamily,
+    consume_ok_tps: MetricFamily,
+    consume_failed_tps: MetricFamily,
+    consume_failed_messages: MetricFamily,
+    threads: MetricFamily,
+}
+
+impl ConsumerClientFamilies {
+    fn new() -> ConsumerClientFamilies {
+        ConsumerClientFamilies {
+            info: MetricFamily::gauge(
+                "rocketmq_consumer_client_info",
+                "Consumer client connected to the broker.",
+            ),
+            subscription: MetricFamily::gauge(
+                "rocketmq_consumer_client_subscription_info",
+                "Subscription of the consumer client.",
+            ),
+            cached_messages: MetricFamily::gauge(
+                "rocketmq_consumer_client_queue_cached_messages",
+                "Messages pulled and cached in the client process queue.",
+            ),
+            cached_bytes: MetricFamily::gauge(
+                "rocketmq_consumer_client_queue_cached_bytes",
+                "Size of the messages cached in the client process queue, in MiB precision.",
+            ),
+            locked: MetricFamily::gauge(
+                "rocketmq_consumer_client_queue_locked",
+                "Whether the queue is locked by the client for orderly consuming.",
+            ),
+            last_pull: MetricFamily::gauge(
+                "rocketmq_consumer_client_queue_last_pull_timestamp_seconds",
+                "Last time the client pulled messages from the queue.",
+            )
+            .with_aggregation(Aggregation::Max),
+            last_consume: MetricFamily::gauge(
+                "rocketmq_consumer_client_queue_last_consume_timestamp_seconds",
+                "Last time the client consumed messages from the queue.",
+            )
+            .with_aggregation(Aggregation::Max),
+            pull_rt: MetricFamily::gauge(
+                "rocketmq_consumer_client_pull_rt_milliseconds",
+                "Average pull response time of the client.",
+            )
+            .with_aggregation(Aggregation::Max),
+            pull_tps: MetricFamily::gauge(
+                "rocketmq_consumer_client_pull_tps",
+                "Messages pulled by the client per second.",
+            ),
+            consume_rt: MetricFamily::gauge(
+                "rocketmq_consumer_client_consume_rt_milliseconds",
+                "Average time the client spends consuming a message.",
+            )
+            .with_aggregation(Aggregation::Max),
+            consume_ok_tps: MetricFamily::gauge(
+                "rocketmq_consumer_client_consume_ok_tps",
+                "Messages consumed successfully by the client per second.",
+            ),
+            consume_failed_tps: MetricFamily::gauge(
+                "rocketmq_consumer_client_consume_failed_tps",
+                "Messages failed to consume by the client per second.",
+            ),
+            consume_failed_messages: MetricFamily::gauge(
+                "rocketmq_consumer_client_consume_failed_messages",
+                "Messages failed to consume by the client in the last hour.",
+            ),
+            threads: MetricFamily::gauge(
+                "rocketmq_consumer_client_threads",
+                "Threads of the client process by state, only available when jstack is enabled.",
+            ),
+        }
+    }
+
+    fn add(
+        &mut self,
+        group: &str,
+        connection: &ConsumerConnection,
+        client: &ClientConnection,
+        running_info: &ConsumerRunningInfo,
+    ) {
+        let client_id = client.client_id();
+        let consume_orderly = running_info
+            .property(PROP_CONSUMEORDERLY)
+            .unwrap_or_default();
+        let client_version = running_info
+            .property(PROP_CLIENT_VERSION)
+            .unwrap_or_default();
+        self.info.add(
+            &[
+                ("group", group),
+                ("client_id", client_id),
+                ("client_addr", client.client_addr()),
+                ("language", client.language()),
+                ("client_version", &client_version),
+                ("consume_type", connection.consume_type()),
+                ("message_model", connection.message_model()),
+                ("consume_orderly", &consume_orderly),
+            ],
+            1.0,
+        );
+        for subscription in running_info.subscriptions() {
+            self.subscription.add(
+                &[
+                    ("group", group),
+                    ("client_id", client_id),
+                    ("topic", subscription.topic()),
+                    ("expression", subscription.sub_string()),
+                ],
+                1.0,
+            );
+        }
+
+        let mut queues: Vec<_> = running_info.mq_table().iter().collect();
+        queues.sort_by(|(a, _), (b, _)| {
+            (a.topic(), a.broker_name(), a.queue_id()).cmp(&(
+                b.topic(),
+                b.broker_name(),
+                b.queue_id(),
+            ))
+        });
+        for (mq, process_queue) in queues {
+            let queue_id = mq.queue_id().to_string();
+            let labels = [
+                ("group", group),
+                ("client_id", client_id),
+                ("topic", mq.topic()),
+                ("broker", mq.broker_name()),
+                ("queue_id", queue_id.as_str()),
+            ];
+            self.cached_messages
+                .add(&labels, process_queue.cached_msg_count() as f64);
+            self.cached_bytes.add(
+                &labels,
+                (process_queue.cached_msg_size_in_mib() * 1024 * 1024) as f64,
+            );
+            self.locked
+                .add(&labels, if process_queue.locked() { 1.0 } else { 0.0 });
+            self.last_pull
+                .add(&labels, process_queue.last_pull_timestamp() as f64 / 1000.0);
+            self.last_consume.add(
+                &labels,
+                process_queue.last_consume_timestamp() as f64 / 1000.0,
+            );
+        }
+
+        let mut status: Vec<_> = running_info.status_table().iter().collect();
+        status.sort_by_key(|(topic, _)| *topic);
+        for (topic, status) in status {
+            let labels = [("group", group), ("client_id", client_id), ("topic", topic)];
+            self.pull_rt.add(&labels, status.pull_rt());
+            self.pull_tps.add(&labels, status.pull_tps());
+            self.consume_rt.add(&labels, status.consume_rt());
+            self.consume_ok_tps.add(&labels, status.consume_ok_tps());
+            self.consume_failed_tps
+                .add(&labels, status.consume_failed_tps());
+            self.consume_failed_messages
+                .add(&labels, status.consume_failed_msgs() as f64);
+        }
+
+        for (state, count) in running_info.thread_states() {
+            self.threads.add(
+                &[
+                    ("group", group),
+                    ("client_id", client_id),
+                    ("state", &state),
+                ],
+                count as f64,
+            );
+        }
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        families.push(self.info);
+        families.push(self.subscription);
+        families.push(self.cached_messages);
+        families.push(self.cached_bytes);
+        families.push(self.locked);
+        families.push(self.last_pull);
+        families.push(self.last_consume);
+        families.push(self.pull_rt);
+        families.push(self.pull_tps);
+        families.push(self.consume_rt);
+        families.push(self.consume_ok_tps);
+        families.push(self.consume_failed_tps);
+        families.push(self.consume_failed_messages);
+        families.push(self.threads);
+    }
+}
+
+type TpsParser = fn(&BrokerRuntimeInfoTable) -> Option<WindowTps>;
+
+///
+/// Broker统计的各种TPS，每个TPS按照统计窗口导出
+struct TpsFamilies {
+    families: Vec<(MetricFamily, TpsParser)>,
+}
+
+impl TpsFamilies {
+    fn new() -> TpsFamilies {
+        TpsFamilies {
+            families: vec![
+                (
+                    MetricFamily::gauge(
+                        "rocketmq_broker_put_tps",
+                        "Messages put to the broker per second.",
+                    ),
+                    BrokerRuntimeInfoTable::put_tps,
+                ),
+                (
+                    MetricFamily::gauge(
+                        "rocketmq_broker_get_total_tps",
+                        "Messages pulled from the broker per second.",
+                    ),
+                    BrokerRuntimeInfoTable::get_total_tps,
+                ),
+                (
+                    MetricFamily::gauge(
+                        "rocketmq_broker_get_found_tps",
+                        "Pulled messages per second that were found.",
+                    ),
+                    BrokerRuntimeInfoTable::get_found_tps,
+                ),
+                (
+                    MetricFamily::gauge(
+                        "rocketmq_broker_get_miss_tps",
+                        "Pulled messages per second that were missed.",
+                    ),
+                    BrokerRuntimeInfoTable::get_miss_tps,
+                ),
+                (
+                    MetricFamily::gauge(
+                        "rocketmq_broker_get_transfered_tps",
+                        "Pulled messages per second that were transferred to consumers.",
+                    ),
+                    BrokerRuntimeInfoTable::get_transfered_tps,
+                ),
+            ],
+        }
+    }
+
+    fn add(&mut self, labels: &[(&str, &str)], runtime_info: &BrokerRuntimeInfo) {
+        for (family, tps) in self.families.iter_mut() {
+            if let Some(tps) = tps(runtime_info.table()) {
+                for (window, value) in tps.windows() {
+                    let mut window_labels = labels.to_vec();
+                    window_labels.push(("window", window));
+                    family.add(&window_labels, value);
+                }
+            }
+        }
+    }
+
+    fn push_to(self, families: &mut Vec<MetricFamily>) {
+        families.extend(self.families.into_iter().map(|(family, _)| family));
+    }
+}
+
+///
+/// 计算配置的指纹，排除Broker身份相关的配置项。
+/// 对排序后的`key=value`逐行做FNV-1a哈希，不同的Rust版本和进程之间结果保持一致；
+/// 结果截断到48位，保证转换为f64时没有精度损失
+fn config_fingerprint(config: &ConfigProperties) -> u64 {
+    let mut entries: Vec<(&String, &String)> = config
+        .properties()
+        .iter()
+        .filter(|(k, _)| !BROKER_IDENTITY_KEYS.contains(&k.as_str()))
+        .collect();
+    entries.sort();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (key, value) in entries {
+        for byte in format!("{key}={value}\n").bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash & 0xFFFF_FFFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "mock")]
+    use rocketmq_exporter_rust::{
+        cmd::command::RequestCode,
+        remoting::mock::{cluster_info, MockResponse, MockServer},
+        util::filter::NameFilter,
+    };
+
+    use super::*;
+    #[cfg(feature = "mock")]
+    use crate::metrics::family;
+
+    ///
+    /// 一个Nameserver和一个Broker的模拟集群，只有一个Topic和一个消费者组
+    #[cfg(feature = "mock")]
+    async fn mock_cluster() -> (MockServer, MockServer) {
+        let broker = MockServer::start().await.unwrap();
+        broker
+            .on(
+                RequestCode::GetTOpicStatsInfo,
+                MockResponse::success(
+                    r#"{"offsetTable":{{"brokerName":"broker-a","queueId":0,"topic":"order_topic"}:{"lastUpdateTimestamp":1706162317012,"maxOffset":120,"minOffset":3}}}"#,
+                ),
+            )
+            .on(
+                RequestCode::QueryTopicConsumeByWho,
+                MockResponse::success(r#"{"groupList":["order_group"]}"#),
+            )
+            .on(
+                RequestCode::GetBrokerRuntimeInfo,
+                MockResponse::success(r#"{"table":{"commitLogDiskRatio":"0.08"}}"#),
+            );
+
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv
+            .on(
+                RequestCode::GetBrokerClusterInfo,
+                MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker.addr())),
+            )
+            .on(
+                RequestCode::GetAllTopicListFromNameserver,
+                MockResponse::success(r#"{"topicList":["order_topic"]}"#),
+            )
+            .on(
+                RequestCode::GetRouteInfoByTopic,
+                MockResponse::success(format!(
+                    r#"{{"brokerDatas":[{{"brokerAddrs":{{0:"{}"}},"brokerName":"broker-a","cluster":"DefaultCluster"}}],"queueDatas":[{{"brokerName":"broker-a","perm":6,"readQueueNums":1,"topicSysFlag":0,"writeQueueNums":1}}]}}"#,
+                    broker.addr()
+                )),
+            )
+            .on(
+                RequestCode::GetSystemTopicListFromNs,
+                MockResponse::success(r#"{"topicList":[]}"#),
+            );
+        (namesrv, broker)
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_collect_from_mock_cluster() {
+        let (namesrv, _broker) = mock_cluster().await;
+        let client = Client::connection(namesrv.addr()).await.unwrap();
+        let mut collector = Collector::new(client, ExporterConfig::default());
+        let output = family::encode(&collector.collect().await);
+        assert!(output.contains(
+            r#"rocketmq_topic_queue_max_offset{cluster="DefaultCluster",broker="broker-a",topic="order_topic",topic_type="normal",queue_id="0"} 120"#
+        ));
+        assert!(output.contains(
+            r#"rocketmq_topic_consumer_group_info{topic="order_topic",topic_type="normal",group="order_group"} 1"#
+        ));
+        assert!(output.contains(
+            r#"rocketmq_broker_commitlog_disk_ratio{cluster="DefaultCluster",broker="broker-a"} 0.08"#
+        ));
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_topic_filter_keeps_retry_topics() {
+        let (namesrv, broker) = mock_cluster().await;
+        namesrv.on(
+            RequestCode::GetAllTopicListFromNameserver,
+            MockResponse::success(r#"{"topicList":["order_topic","%RETRY%order_group"]}"#),
+        );
+        // order_topic被过滤，只有重试Topic会查询统计信息
+        broker.on(
+            RequestCode::GetTOpicStatsInfo,
+            MockResponse::success(
+                r#"{"offsetTable":{{"brokerName":"broker-a","queueId":0,"topic":"%RETRY%order_group"}:{"lastUpdateTimestamp":0,"maxOffset":15,"minOffset":0}}}"#,
+            ),
+        );
+        let client = Client::connection(namesrv.addr()).await.unwrap();
+        let config = ExporterConfig {
+            topic_filter: NameFilter::new(&["pay_.*".to_string()], &[]).unwrap(),
+            ..ExporterConfig::default()
+        };
+        let mut collector = Collector::new(client, config);
+        let output = family::encode(&collector.collect().await);
+        assert!(!output.contains(r#"topic="order_topic""#));
+        assert!(output.contains(
+            r#"rocketmq_consumer_retry_messages_written{cluster="DefaultCluster",group="order_group"} 15"#
+        ));
+    }
+
+    #[test]
+    fn test_config_fingerprint_ignore_identity_keys() {
+        let a =
+            ConfigProperties::parse("brokerName=broker-a\nbrokerId=0\nflushDiskType=ASYNC_FLUSH\n");
+        let b =
+            ConfigProperties::parse("flushDiskType=ASYNC_FLUSH\nbrokerName=broker-b\nbrokerId=0\n");
+        let c =
+            ConfigProperties::parse("brokerName=broker-a\nbrokerId=0\nflushDiskType=SYNC_FLUSH\n");
+        assert_eq!(config_fingerprint(&a), config_fingerprint(&b));
+        assert_eq!(config_fingerprint(&a), 0x3d69_95a3_2266);
+        assert_ne!(config_fingerprint(&a), config_fingerprint(&c));
+    }
+}
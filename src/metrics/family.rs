@@ -0,0 +1,326 @@
+use std::{collections::HashMap, fmt::Write};
+
+///
+/// Prometheus的指标类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+        }
+    }
+}
+
+///
+/// 超过基数限制的时候合并采样点的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Sum,
+    Max,
+}
+
+///
+/// 执行基数限制的结果：被合并掉的序列数和被丢弃的序列数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitResult {
+    pub aggregated: usize,
+    pub dropped: usize,
+}
+
+///
+/// 指标的一个采样点
+#[derive(Debug, Clone)]
+pub struct Sample {
+    ///
+    /// 指标名字的后缀，histogram使用: _bucket、_sum、_count
+    suffix: &'static str,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+///
+/// 同一个名字的指标集合，对应Prometheus文本格式的一个HELP/TYPE块
+#[derive(Debug, Clone)]
+pub struct MetricFamily {
+    name: String,
+    help: String,
+    metric_type: MetricType,
+    aggregation: Aggregation,
+    samples: Vec<Sample>,
+}
+
+impl MetricFamily {
+    pub fn new(name: &str, help: &str, metric_type: MetricType) -> MetricFamily {
+        MetricFamily {
+            name: name.to_string(),
+            help: help.to_string(),
+            metric_type,
+            aggregation: Aggregation::Sum,
+            samples: vec![],
+        }
+    }
+
+    pub fn gauge(name: &str, help: &str) -> MetricFamily {
+        Self::new(name, help, MetricType::Gauge)
+    }
+
+    pub fn counter(name: &str, help: &str) -> MetricFamily {
+        Self::new(name, help, MetricType::Counter)
+    }
+
+    pub fn histogram(name: &str, help: &str) -> MetricFamily {
+        Self::new(name, help, MetricType::Histogram)
+    }
+
+    ///
+    /// 设置超过基数限制时的合并方式，默认求和，延迟和时间戳这类指标应该取最大值
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> MetricFamily {
+        self.aggregation = aggregation;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add(&mut self, labels: &[(&str, &str)], value: f64) {
+        self.push_sample("", labels, value);
+    }
+
+    ///
+    /// 添加一个histogram，buckets是(上界, 累计次数)，需要包含+Inf
+    pub fn add_histogram(
+        &mut self,
+        labels: &[(&str, &str)],
+        buckets: &[(f64, u64)],
+        sum: f64,
+        count: u64,
+    ) {
+        for (upper_bound, cumulative) in buckets.iter() {
+            let le = format_value(*upper_bound);
+            let mut bucket_labels = labels.to_vec();
+            bucket_labels.push(("le", &le));
+            self.push_sample("_bucket", &bucket_labels, *cumulative as f64);
+        }
+        self.push_sample("_sum", labels, sum);
+        self.push_sample("_count", labels, count as f64);
+    }
+
+    fn push_sample(&mut self, suffix: &'static str, labels: &[(&str, &str)], value: f64) {
+        self.samples.push(Sample {
+            suffix,
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            value,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    ///
+    /// 序列数超过limit的时候依次去掉aggregate_labels中的标签，把剩余标签相同的采样点合并；
+    /// 合并以后仍然超过limit的时候丢弃多出来的序列。histogram的bucket不能合并，不做限制
+    pub fn limit(&mut self, limit: usize, aggregate_labels: &[&str]) -> LimitResult {
+        let mut result = LimitResult::default();
+        if self.metric_type == MetricType::Histogram {
+            return result;
+        }
+        for label in aggregate_labels {
+            if self.samples.len() <= limit {
+                return result;
+            }
+            let before = self.samples.len();
+            self.aggregate_without(label);
+            result.aggregated += before - self.samples.len();
+        }
+        if self.samples.len() > limit {
+            result.dropped = self.samples.len() - limit;
+            self.samples.truncate(limit);
+        }
+        result
+    }
+
+    fn aggregate_without(&mut self, label: &str) {
+        let mut index: HashMap<_, usize> = HashMap::new();
+        let mut samples: Vec<Sample> = vec![];
+        for mut sample in self.samples.drain(..) {
+            sample.labels.retain(|(key, _)| key != label);
+            let key = (sample.suffix, sample.labels.clone());
+            match index.get(&key) {
+                Some(i) => {
+                    let merged = &mut samples[*i];
+                    merged.value = match self.aggregation {
+                        Aggregation::Sum => merged.value + sample.value,
+                        Aggregation::Max => merged.value.max(sample.value),
+                    };
+                }
+                None => {
+                    index.insert(key, samples.len());
+                    samples.push(sample);
+                }
+            }
+        }
+        self.samples = samples;
+    }
+
+    ///
+    /// 按照Prometheus的文本格式编码
+    pub fn encode(&self, out: &mut String) {
+        if self.is_empty() {
+            return;
+        }
+        let _ = writeln!(out, "# HELP {} {}", self.name, escape_help(&self.help));
+        let _ = writeln!(out, "# TYPE {} {}", self.name, self.metric_type.as_str());
+        for sample in self.samples.iter() {
+            out.push_str(&self.name);
+            out.push_str(sample.suffix);
+            if !sample.labels.is_empty() {
+                out.push('{');
+                for (i, (key, value)) in sample.labels.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    let _ = write!(out, "{}=\"{}\"", key, escape_label_value(value));
+                }
+                out.push('}');
+            }
+            let _ = writeln!(out, " {}", format_value(sample.value));
+        }
+    }
+}
+
+///
+/// 编码所有的指标
+pub fn encode(families: &[MetricFamily]) -> String {
+    let mut out = String::new();
+    for family in families.iter() {
+        family.encode(&mut out);
+    }
+    out
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        String::from("NaN")
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            String::from("+Inf")
+        } else {
+            String::from("-Inf")
+        }
+    } else {
+        format!("{}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_metric_family() {
+        let mut family = MetricFamily::gauge("rocketmq_broker_config_info", "Broker config");
+        family.add(
+            &[
+                ("broker", "broker-a"),
+                ("key", "brokerRole"),
+                ("value", "a\"b"),
+            ],
+            1.0,
+        );
+        family.add(&[], 0.5);
+        let text = encode(&[family, MetricFamily::counter("empty_total", "Empty")]);
+        assert_eq!(
+            text,
+            "# HELP rocketmq_broker_config_info Broker config\n\
+             # TYPE rocketmq_broker_config_info gauge\n\
+             rocketmq_broker_config_info{broker=\"broker-a\",key=\"brokerRole\",value=\"a\\\"b\"} 1\n\
+             rocketmq_broker_config_info 0.5\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_histogram() {
+        let mut family = MetricFamily::histogram("rocketmq_put_latency_seconds", "Put latency");
+        family.add_histogram(
+            &[("broker", "broker-a")],
+            &[(0.01, 3), (f64::INFINITY, 4)],
+            0.5,
+            4,
+        );
+        assert_eq!(
+            encode(&[family]),
+            "# HELP rocketmq_put_latency_seconds Put latency\n\
+             # TYPE rocketmq_put_latency_seconds histogram\n\
+             rocketmq_put_latency_seconds_bucket{broker=\"broker-a\",le=\"0.01\"} 3\n\
+             rocketmq_put_latency_seconds_bucket{broker=\"broker-a\",le=\"+Inf\"} 4\n\
+             rocketmq_put_latency_seconds_sum{broker=\"broker-a\"} 0.5\n\
+             rocketmq_put_latency_seconds_count{broker=\"broker-a\"} 4\n"
+        );
+    }
+
+    #[test]
+    fn test_limit_aggregate_queue_label() {
+        let mut family = MetricFamily::gauge("rocketmq_topic_queue_max_offset", "Max offset");
+        for topic in ["a", "b"] {
+            for queue_id in ["0", "1", "2"] {
+                family.add(&[("topic", topic), ("queue_id", queue_id)], 10.0);
+            }
+        }
+        let result = family.limit(2, &["queue_id"]);
+        assert_eq!(
+            result,
+            LimitResult {
+                aggregated: 4,
+                dropped: 0
+            }
+        );
+        assert_eq!(
+            encode(&[family.clone()]),
+            "# HELP rocketmq_topic_queue_max_offset Max offset\n\
+             # TYPE rocketmq_topic_queue_max_offset gauge\n\
+             rocketmq_topic_queue_max_offset{topic=\"a\"} 30\n\
+             rocketmq_topic_queue_max_offset{topic=\"b\"} 30\n"
+        );
+
+        let mut delay = MetricFamily::gauge("delay", "Delay").with_aggregation(Aggregation::Max);
+        delay.add(&[("topic", "a"), ("queue_id", "0")], 1.0);
+        delay.add(&[("topic", "a"), ("queue_id", "1")], 5.0);
+        delay.add(&[("topic", "b"), ("queue_id", "0")], 2.0);
+        let result = delay.limit(1, &["queue_id"]);
+        assert_eq!(
+            result,
+            LimitResult {
+                aggregated: 1,
+                dropped: 1
+            }
+        );
+        assert_eq!(
+            encode(&[delay]),
+            "# HELP delay Delay\n# TYPE delay gauge\ndelay{topic=\"a\"} 5\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use rocketmq_exporter_rust::remoting::response::LatencyBucket;
+
+///
+/// 把Broker的耗时分布累加成单调递增的histogram。
+/// putMessageDistributeTime是Broker上一个完整分钟的耗时分布快照，不是累计值：
+/// 快照发生变化的时候认为是新的一分钟，全部计数作为新增；和上一次相同的时候认为是同一分钟被重复采集，忽略。
+/// 连续两分钟的分布完全相同的时候后一分钟会被漏掉，采集间隔大于一分钟的时候中间的分钟也会被漏掉
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    upper_bounds: Vec<f64>,
+    previous: Vec<u64>,
+    totals: Vec<u64>,
+    sum: f64,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, buckets: &[LatencyBucket]) {
+        let upper_bounds: Vec<f64> = buckets.iter().map(|b| b.upper_bound()).collect();
+        if upper_bounds != self.upper_bounds {
+            *self = LatencyHistogram {
+                upper_bounds,
+                previous: vec![0; buckets.len()],
+                totals: vec![0; buckets.len()],
+                sum: 0.0,
+            };
+        }
+
+        let counts: Vec<u64> = buckets.iter().map(|b| b.count()).collect();
+        if counts == self.previous {
+            return;
+        }
+        for (i, bucket) in buckets.iter().enumerate() {
+            self.totals[i] += bucket.count();
+            self.sum += bucket.count() as f64 * Self::estimate(bucket);
+        }
+        self.previous = counts;
+    }
+
+    ///
+    /// 累计的buckets: (上界, 小于等于上界的次数)
+    pub fn buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0;
+        self.upper_bounds
+            .iter()
+            .zip(self.totals.iter())
+            .map(|(upper_bound, total)| {
+                cumulative += total;
+                (*upper_bound, cumulative)
+            })
+            .collect()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.totals.iter().sum()
+    }
+
+    ///
+    /// Broker没有提供耗时总和，使用区间的中点估算
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    fn estimate(bucket: &LatencyBucket) -> f64 {
+        if bucket.upper_bound().is_infinite() {
+            bucket.lower_bound()
+        } else {
+            (bucket.lower_bound() + bucket.upper_bound()) / 2.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buckets(counts: [u64; 3]) -> Vec<LatencyBucket> {
+        vec![
+            LatencyBucket::parse(&format!("[0~10ms]:{}", counts[0])).unwrap(),
+            LatencyBucket::parse(&format!("[10~50ms]:{}", counts[1])).unwrap(),
+            LatencyBucket::parse(&format!("[10s~]:{}", counts[2])).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_accumulate_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(&buckets([4, 2, 0]));
+        histogram.observe(&buckets([6, 2, 1]));
+        assert_eq!(
+            histogram.buckets(),
+            vec![(0.01, 10), (0.05, 14), (f64::INFINITY, 15)]
+        );
+
+        // 同一分钟的快照被重复采集
+        histogram.observe(&buckets([6, 2, 1]));
+        assert_eq!(histogram.count(), 15);
+
+        // 下一分钟的计数比上一分钟小
+        histogram.observe(&buckets([1, 0, 0]));
+        assert_eq!(
+            histogram.buckets(),
+            vec![(0.01, 11), (0.05, 15), (f64::INFINITY, 16)]
+        );
+        assert_eq!(histogram.count(), 16);
+        assert!((histogram.sum() - (11.0 * 0.005 + 4.0 * 0.03 + 10.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod collector;
+pub mod family;
+pub mod histogram;
+pub mod server;
+pub mod snapshot;
@@ -0,0 +1,156 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tokio::{net::TcpListener, sync::Mutex};
+
+use super::{collector::Collector, family};
+
+#[derive(Clone)]
+struct AppState {
+    collector: Arc<Mutex<Collector>>,
+    ///
+    /// 序列化以后的快照以及生成的时间，生成快照需要遍历整个集群，缓存以免/api/snapshot的请求长时间阻塞/metrics
+    snapshot: Arc<Mutex<Option<(Instant, Bytes)>>>,
+    snapshot_ttl: Duration,
+}
+
+///
+/// 启动HTTP服务，Prometheus通过/metrics拉取指标，/api/snapshot以JSON格式返回整个集群的快照，快照缓存snapshot_ttl
+pub async fn serve(
+    addr: &str,
+    collector: Collector,
+    snapshot_ttl: Duration,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app(collector, snapshot_ttl)).await
+}
+
+fn app(collector: Collector, snapshot_ttl: Duration) -> Router {
+    let state = AppState {
+        collector: Arc::new(Mutex::new(collector)),
+        snapshot: Arc::new(Mutex::new(None)),
+        snapshot_ttl,
+    };
+    Router::new()
+        .route("/metrics", get(metrics))
+        .route("/api/snapshot", get(snapshot))
+        .with_state(state)
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let families = state.collector.lock().await.collect().await;
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        family::encode(&families),
+    )
+}
+
+///
+/// 缓存过期的时候重新生成快照，同时到达的请求等待同一次生成的结果
+async fn snapshot(State(state): State<AppState>) -> Response {
+    let mut cached = state.snapshot.lock().await;
+    let body = match cached.as_ref() {
+        Some((created, body)) if created.elapsed() < state.snapshot_ttl => body.clone(),
+        _ => match state.collector.lock().await.snapshot().await {
+            Some(snapshot) => {
+                let body = Bytes::from(serde_json::to_vec(&snapshot).unwrap());
+                *cached = Some((Instant::now(), body.clone()));
+                body
+            }
+            None => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "failed to get cluster info from nameserver",
+                )
+                    .into_response()
+            }
+        },
+    };
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use rocketmq_exporter_rust::{
+        cmd::command::RequestCode,
+        remoting::{
+            client::Client,
+            mock::{cluster_info, MockResponse, MockServer},
+        },
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::config::ExporterConfig;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_serve_mock_cluster() {
+        let broker = MockServer::start().await.unwrap();
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv
+            .on(
+                RequestCode::GetBrokerClusterInfo,
+                MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker.addr())),
+            )
+            .on(
+                RequestCode::GetAllTopicListFromNameserver,
+                MockResponse::success(r#"{"topicList":[]}"#),
+            );
+        broker.on(
+            RequestCode::GetBrokerRuntimeInfo,
+            MockResponse::success(r#"{"table":{"commitLogDiskRatio":"0.08"}}"#),
+        );
+
+        let client = Client::connection(namesrv.addr()).await.unwrap();
+        let collector = Collector::new(client, ExporterConfig::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app(collector, Duration::from_secs(60))).await
+        });
+
+        let metrics = get(addr, "/metrics").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK"));
+        assert!(metrics.contains("content-type: text/plain; version=0.0.4"));
+        assert!(metrics.contains(
+            r#"rocketmq_broker_commitlog_disk_ratio{cluster="DefaultCluster",broker="broker-a"} 0.08"#
+        ));
+
+        let snapshot = get(addr, "/api/snapshot").await;
+        assert!(snapshot.starts_with("HTTP/1.1 200 OK"));
+        assert!(snapshot.contains("content-type: application/json"));
+        assert!(snapshot.contains(r#""commitLogDiskRatio":"0.08""#));
+
+        // 缓存没有过期的时候不再访问集群
+        let requests = broker.requests(RequestCode::GetBrokerRuntimeInfo).len();
+        let cached = get(addr, "/api/snapshot").await;
+        assert_eq!(
+            cached.split_once("\r\n\r\n").unwrap().1,
+            snapshot.split_once("\r\n\r\n").unwrap().1
+        );
+        assert_eq!(
+            broker.requests(RequestCode::GetBrokerRuntimeInfo).len(),
+            requests
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use rocketmq_exporter_rust::remoting::response::{
+    BrokerInformation, BrokerRuntimeInfo, TopicRouteInformation, TopicStats,
+};
+
+///
+/// 整个集群的快照，通过/api/snapshot以JSON格式提供给内部的监控面板
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    ///
+    /// 获取快照的时间，单位是毫秒
+    pub timestamp: i64,
+    pub broker_info: BrokerInformation,
+    ///
+    /// 每个Master Broker的运行时信息，key是Broker的地址
+    pub broker_runtime: BTreeMap<String, BrokerRuntimeInfo>,
+    pub topics: BTreeMap<String, TopicSnapshot>,
+}
+
+///
+/// 一个Topic的路由、所有Broker上的队列offset以及订阅的消费者组
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopicSnapshot {
+    pub topic_type: &'static str,
+    pub route: TopicRouteInformation,
+    pub stats: Option<TopicStats>,
+    pub consumer_groups: BTreeSet<String>,
+}
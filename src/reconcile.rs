@@ -0,0 +1,636 @@
+//!
+//! 声明式地管理集群的Topic和订阅组：读取YAML描述的期望状态，和Broker上的实际状态比较得到计划，确认以后执行。
+//! 只会创建和更新，不会删除没有在YAML中声明的Topic和订阅组
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use rocketmq_exporter_rust::remoting::{
+    admin::{SubscriptionGroupConfig, TopicConfig},
+    client::Client,
+};
+
+///
+/// YAML描述的期望状态，key是集群的名字，例如:
+/// ```yaml
+/// clusters:
+///   DefaultCluster:
+///     topics:
+///       - topicName: order_topic
+///         readQueueNums: 8
+///         writeQueueNums: 8
+///     groups:
+///       - groupName: order_group
+///         retryMaxTimes: 16
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct DesiredState {
+    clusters: BTreeMap<String, ClusterState>,
+}
+
+///
+/// 一个集群期望的Topic和订阅组
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClusterState {
+    topics: Vec<Declared<TopicConfig>>,
+    groups: Vec<Declared<SubscriptionGroupConfig>>,
+}
+
+///
+/// YAML中声明的一个Topic或者订阅组。fields是YAML中设置的字段，只有这些字段参与比较；
+/// 更新的时候把这些字段覆盖到Broker上的实际配置，创建的时候覆盖到默认配置
+#[derive(Debug, Clone)]
+pub struct Declared<T> {
+    config: T,
+    fields: Map<String, Value>,
+}
+
+impl<'de, T: Default + Serialize + DeserializeOwned> Deserialize<'de> for Declared<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = Map::deserialize(deserializer)?;
+        let config = overlay(&T::default(), &fields).map_err(serde::de::Error::custom)?;
+        Ok(Declared { config, fields })
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Declared<T> {
+    ///
+    /// 把YAML中设置的字段覆盖到Broker上的实际配置，没有设置的字段保持Broker上的值
+    fn apply_to(&self, live: &T) -> Result<T, serde_json::Error> {
+        overlay(live, &self.fields)
+    }
+
+    ///
+    /// YAML中设置的字段和Broker上的实际值不一致的字段，格式: readQueueNums: 8 -> 16
+    fn diff(&self, live: &T) -> Vec<String> {
+        let desired = to_map(&self.config);
+        let live = to_map(live);
+        self.fields
+            .keys()
+            .filter_map(|name| {
+                let desired = desired.get(name).unwrap_or(&Value::Null);
+                let live = live.get(name).unwrap_or(&Value::Null);
+                if live == desired {
+                    None
+                } else {
+                    Some(format!(
+                        "{name}: {} -> {}",
+                        display_value(live),
+                        display_value(desired)
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+fn overlay<T: Serialize + DeserializeOwned>(
+    base: &T,
+    fields: &Map<String, Value>,
+) -> Result<T, serde_json::Error> {
+    let mut map = to_map(base);
+    map.extend(fields.clone());
+    serde_json::from_value(Value::Object(map))
+}
+
+fn to_map<T: Serialize>(config: &T) -> Map<String, Value> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl DesiredState {
+    ///
+    /// 解析YAML并检查Topic和订阅组的名字不为空、Topic的队列数大于0。
+    /// Topic的配置通过请求头发送，只支持建模的字段；`order`需要写入Nameserver的orderConf，Broker不会处理，同样不支持
+    pub fn parse(source: &str) -> Result<DesiredState, String> {
+        let state: DesiredState = serde_yaml::from_str(source).map_err(|e| e.to_string())?;
+        let topic_fields = to_map(&TopicConfig::default());
+        for (cluster, desired) in state.clusters.iter() {
+            for topic in desired.topics.iter() {
+                let config = &topic.config;
+                if config.topic.is_empty() {
+                    return Err(format!("cluster {cluster}: topicName must not be empty"));
+                }
+                if config.read_queue_nums <= 0 || config.write_queue_nums <= 0 {
+                    return Err(format!(
+                        "cluster {cluster}: topic {}: readQueueNums and writeQueueNums must be greater than 0",
+                        config.topic
+                    ));
+                }
+                if topic.fields.contains_key("order") {
+                    return Err(format!(
+                        "cluster {cluster}: topic {}: order is not supported, ordered topics need orderConf in the nameserver KV config",
+                        config.topic
+                    ));
+                }
+                if let Some(field) = topic
+                    .fields
+                    .keys()
+                    .find(|field| !topic_fields.contains_key(*field))
+                {
+                    return Err(format!(
+                        "cluster {cluster}: topic {}: unknown field {field}",
+                        config.topic
+                    ));
+                }
+            }
+            if desired
+                .groups
+                .iter()
+                .any(|group| group.config.group_name.is_empty())
+            {
+                return Err(format!("cluster {cluster}: groupName must not be empty"));
+            }
+        }
+        Ok(state)
+    }
+
+    pub fn load(path: &str) -> Result<DesiredState, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::parse(&source).map_err(|e| format!("{path}: {e}"))
+    }
+}
+
+///
+/// 一个Broker上的实际状态
+pub struct BrokerState {
+    addr: String,
+    topics: HashMap<String, TopicConfig>,
+    groups: HashMap<String, SubscriptionGroupConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Update,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resource {
+    Topic(TopicConfig),
+    Group(SubscriptionGroupConfig),
+}
+
+impl Resource {
+    fn kind_and_name(&self) -> (&str, &str) {
+        match self {
+            Resource::Topic(topic) => ("topic", topic.topic.as_str()),
+            Resource::Group(group) => ("group", group.group_name.as_str()),
+        }
+    }
+}
+
+///
+/// 变更中的一个Broker，resource是发送给这个Broker的完整配置，diff是和实际状态不一致的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    addr: String,
+    resource: Resource,
+    diff: Vec<String>,
+}
+
+///
+/// 计划中的一个变更，targets是需要变更的Broker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    cluster: String,
+    action: Action,
+    targets: Vec<Target>,
+}
+
+impl Change {
+    ///
+    /// 需要变更的Broker的地址
+    pub fn brokers(&self) -> Vec<&str> {
+        self.targets.iter().map(|t| t.addr.as_str()).collect()
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (kind, name) = match self.targets.first() {
+            Some(target) => target.resource.kind_and_name(),
+            None => ("", ""),
+        };
+        let sign = match self.action {
+            Action::Create => "+",
+            Action::Update => "~",
+        };
+        write!(
+            f,
+            "{sign} {kind} {name} [cluster: {}, brokers: {}]",
+            self.cluster,
+            self.brokers().join(",")
+        )?;
+        for target in self.targets.iter() {
+            for field in target.diff.iter() {
+                write!(f, "\n    {} {field}", target.addr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// 比较集群的期望状态和每个Broker的实际状态，得到需要执行的变更
+pub fn plan_cluster(
+    cluster: &str,
+    desired: &ClusterState,
+    brokers: &[BrokerState],
+) -> Result<Vec<Change>, String> {
+    let mut changes = vec![];
+    for topic in desired.topics.iter() {
+        let live: Vec<_> = brokers
+            .iter()
+            .map(|b| (b.addr.clone(), b.topics.get(&topic.config.topic)))
+            .collect();
+        changes.extend(plan_resource(cluster, topic, &live, Resource::Topic)?);
+    }
+    for group in desired.groups.iter() {
+        let live: Vec<_> = brokers
+            .iter()
+            .map(|b| (b.addr.clone(), b.groups.get(&group.config.group_name)))
+            .collect();
+        changes.extend(plan_resource(cluster, group, &live, Resource::Group)?);
+    }
+    Ok(changes)
+}
+
+///
+/// 缺少资源的Broker需要创建，已经有资源但是字段不一致的Broker需要更新，两者可能同时存在
+fn plan_resource<T: Clone + Serialize + DeserializeOwned>(
+    cluster: &str,
+    declared: &Declared<T>,
+    live: &[(String, Option<&T>)],
+    resource: fn(T) -> Resource,
+) -> Result<Vec<Change>, String> {
+    let mut missing = vec![];
+    let mut drifted = vec![];
+    for (addr, l) in live.iter() {
+        match l {
+            None => missing.push(Target {
+                addr: addr.clone(),
+                resource: resource(declared.config.clone()),
+                diff: vec![],
+            }),
+            Some(l) => {
+                let diff = declared.diff(l);
+                if !diff.is_empty() {
+                    let config = declared.apply_to(l).map_err(|e| format!("{addr}: {e}"))?;
+                    drifted.push(Target {
+                        addr: addr.clone(),
+                        resource: resource(config),
+                        diff,
+                    });
+                }
+            }
+        }
+    }
+    let mut changes = vec![];
+    if !missing.is_empty() {
+        changes.push(Change {
+            cluster: cluster.to_string(),
+            action: Action::Create,
+            targets: missing,
+        });
+    }
+    if !drifted.is_empty() {
+        changes.push(Change {
+            cluster: cluster.to_string(),
+            action: Action::Update,
+            targets: drifted,
+        });
+    }
+    Ok(changes)
+}
+
+///
+/// 获取每个集群所有Master Broker的实际状态并计算计划，任何一个Broker获取失败的时候返回错误
+pub async fn plan(client: &mut Client, state: &DesiredState) -> Result<Vec<Change>, String> {
+    let mut changes = vec![];
+    for (cluster, desired) in state.clusters.iter() {
+        let addrs = client.cluster_master_addrs(cluster).await;
+        if addrs.is_empty() {
+            return Err(format!("cluster {cluster} has no master broker"));
+        }
+        let mut brokers = vec![];
+        for addr in addrs {
+            let topics = client
+                .topic_configs(&addr)
+                .await
+                .ok_or_else(|| format!("failed to get topic configs from {addr}"))?;
+            let groups = client
+                .subscription_groups(&addr)
+                .await
+                .ok_or_else(|| format!("failed to get subscription groups from {addr}"))?;
+            brokers.push(BrokerState {
+                addr,
+                topics,
+                groups,
+            });
+        }
+        changes.extend(plan_cluster(cluster, desired, &brokers)?);
+    }
+    Ok(changes)
+}
+
+///
+/// 执行计划中的变更，每个Broker使用计划中为它生成的配置；全部成功的时候返回true
+pub async fn apply(client: &mut Client, changes: &[Change]) -> bool {
+    let mut success = true;
+    for change in changes.iter() {
+        let mut result = true;
+        for target in change.targets.iter() {
+            result &= match &target.resource {
+                Resource::Topic(topic) => {
+                    client
+                        .create_or_update_topic_in_broker(&target.addr, topic)
+                        .await
+                }
+                Resource::Group(group) => {
+                    client
+                        .create_or_update_subscription_group_in_broker(&target.addr, group)
+                        .await
+                }
+            };
+        }
+        println!("{} {}", if result { "done" } else { "failed" }, change);
+        success &= result;
+    }
+    success
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "mock")]
+    use rocketmq_exporter_rust::{
+        cmd::command::RequestCode,
+        remoting::mock::{cluster_info, MockResponse, MockServer},
+    };
+
+    use rocketmq_exporter_rust::remoting::admin::PERM_READ;
+
+    use super::*;
+
+    #[test]
+    fn test_plan_cluster() {
+        let state = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - topicName: order_topic
+        readQueueNums: 16
+        writeQueueNums: 16
+      - topicName: pay_topic
+    groups:
+      - groupName: order_group
+"#,
+        )
+        .unwrap();
+        let desired = &state.clusters["DefaultCluster"];
+        assert_eq!(desired.topics[1].config.read_queue_nums, 8);
+
+        let mut live_topic = TopicConfig::new("order_topic".to_string());
+        live_topic.read_queue_nums = 8;
+        live_topic.write_queue_nums = 16;
+        let brokers = vec![
+            BrokerState {
+                addr: "10.20.141.73:10911".to_string(),
+                topics: HashMap::from([
+                    ("order_topic".to_string(), live_topic.clone()),
+                    (
+                        "pay_topic".to_string(),
+                        TopicConfig::new("pay_topic".to_string()),
+                    ),
+                ]),
+                groups: HashMap::from([(
+                    "order_group".to_string(),
+                    SubscriptionGroupConfig::new("order_group".to_string()),
+                )]),
+            },
+            BrokerState {
+                addr: "10.20.141.72:10911".to_string(),
+                topics: HashMap::from([(
+                    "pay_topic".to_string(),
+                    TopicConfig::new("pay_topic".to_string()),
+                )]),
+                groups: HashMap::from([(
+                    "order_group".to_string(),
+                    SubscriptionGroupConfig::new("order_group".to_string()),
+                )]),
+            },
+        ];
+        // 第二个Broker缺少order_topic，第一个Broker上的order_topic不一致
+        let changes = plan_cluster("DefaultCluster", desired, &brokers).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].action, Action::Create);
+        assert_eq!(changes[0].brokers(), vec!["10.20.141.72:10911"]);
+        assert_eq!(changes[1].action, Action::Update);
+        assert_eq!(changes[1].brokers(), vec!["10.20.141.73:10911"]);
+
+        let changes = plan_cluster("DefaultCluster", desired, &brokers[..1]).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].action, Action::Update);
+        assert_eq!(
+            changes[0].to_string(),
+            "~ topic order_topic [cluster: DefaultCluster, brokers: 10.20.141.73:10911]\n    10.20.141.73:10911 readQueueNums: 8 -> 16"
+        );
+
+        // 每个Broker上不一致的字段分别显示
+        let mut brokers = brokers;
+        let mut other_topic = live_topic.clone();
+        other_topic.read_queue_nums = 16;
+        other_topic.write_queue_nums = 4;
+        brokers[1]
+            .topics
+            .insert("order_topic".to_string(), other_topic);
+        let changes = plan_cluster("DefaultCluster", desired, &brokers).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].to_string(),
+            "~ topic order_topic [cluster: DefaultCluster, brokers: 10.20.141.73:10911,10.20.141.72:10911]\n    10.20.141.73:10911 readQueueNums: 8 -> 16\n    10.20.141.72:10911 writeQueueNums: 4 -> 16"
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_undeclared_fields() {
+        let state = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - topicName: order_topic
+        topicSysFlag: 1
+        topicFilterType: MULTI_TAG
+    groups:
+      - groupName: order_group
+        retryMaxTimes: 3
+"#,
+        )
+        .unwrap();
+        let desired = &state.clusters["DefaultCluster"];
+
+        // Broker上的Topic是只读的，有5.x的attributes；订阅组有没有建模的字段
+        let mut live_topic = TopicConfig::new("order_topic".to_string());
+        live_topic.read_queue_nums = 4;
+        live_topic.perm = PERM_READ;
+        live_topic.extra.insert(
+            "attributes".to_string(),
+            serde_json::json!({"message.type": "NORMAL"}),
+        );
+        let mut live_group = SubscriptionGroupConfig::new("order_group".to_string());
+        live_group.consume_enable = false;
+        live_group
+            .extra
+            .insert("consumeTimeoutMinute".to_string(), Value::from(30));
+        let brokers = vec![BrokerState {
+            addr: "10.20.141.73:10911".to_string(),
+            topics: HashMap::from([("order_topic".to_string(), live_topic.clone())]),
+            groups: HashMap::from([("order_group".to_string(), live_group.clone())]),
+        }];
+
+        let changes = plan_cluster("DefaultCluster", desired, &brokers).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[0].to_string(),
+            "~ topic order_topic [cluster: DefaultCluster, brokers: 10.20.141.73:10911]\n    10.20.141.73:10911 topicFilterType: SINGLE_TAG -> MULTI_TAG\n    10.20.141.73:10911 topicSysFlag: 0 -> 1"
+        );
+        let mut expected_topic = live_topic;
+        expected_topic.topic_sys_flag = 1;
+        expected_topic.topic_filter_type = "MULTI_TAG".to_string();
+        assert_eq!(
+            changes[0].targets[0].resource,
+            Resource::Topic(expected_topic)
+        );
+        let mut expected_group = live_group;
+        expected_group.retry_max_times = 3;
+        assert_eq!(
+            changes[1].targets[0].resource,
+            Resource::Group(expected_group)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_state() {
+        let error = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - readQueueNums: 8
+"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error, "cluster DefaultCluster: topicName must not be empty");
+
+        let error = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - topicName: order_topic
+        writeQueueNums: 0
+"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "cluster DefaultCluster: topic order_topic: readQueueNums and writeQueueNums must be greater than 0"
+        );
+
+        let error = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    groups:
+      - retryMaxTimes: 3
+"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error, "cluster DefaultCluster: groupName must not be empty");
+
+        let error = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - topicName: order_topic
+        order: true
+"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "cluster DefaultCluster: topic order_topic: order is not supported, ordered topics need orderConf in the nameserver KV config"
+        );
+
+        let error = DesiredState::parse(
+            r#"
+clusters:
+  DefaultCluster:
+    topics:
+      - topicName: order_topic
+        readQueueNum: 8
+"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "cluster DefaultCluster: topic order_topic: unknown field readQueueNum"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_apply_only_to_planned_brokers() {
+        let broker_a = MockServer::start().await.unwrap();
+        let broker_b = MockServer::start().await.unwrap();
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker_a.addr())),
+        );
+        for broker in [&broker_a, &broker_b] {
+            broker.on(RequestCode::UpdateAndCreateTopic, MockResponse::success(""));
+        }
+        let mut client = Client::connection(namesrv.addr()).await.unwrap();
+        let change = Change {
+            cluster: "DefaultCluster".to_string(),
+            action: Action::Create,
+            targets: vec![Target {
+                addr: broker_b.addr().to_string(),
+                resource: Resource::Topic(TopicConfig::new("order_topic".to_string())),
+                diff: vec![],
+            }],
+        };
+        assert!(apply(&mut client, &[change]).await);
+        assert!(broker_a
+            .requests(RequestCode::UpdateAndCreateTopic)
+            .is_empty());
+        assert_eq!(
+            broker_b.requests(RequestCode::UpdateAndCreateTopic)[0]["topic"],
+            "order_topic"
+        );
+    }
+}
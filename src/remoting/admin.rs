@@ -0,0 +1,574 @@
+//!
+//! 修改集群状态的管理操作，只有开启admin feature的时候才会编译
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    cmd::command::{
+        CreateTopicRequestHeader, DeleteSubscriptionGroupRequestHeader,
+        DeleteTopicFromNamesrvRequestHeader, DeleteTopicRequestHeader, RemotingCommand,
+        RequestCode, ResetOffsetRequestHeader,
+    },
+    util::json::Tokenizer,
+};
+
+use super::{
+    client::Client,
+    response::{MessageQueue, ResetOffsetBody},
+};
+
+///
+/// Topic的读写权限
+pub const PERM_INHERIT: i32 = 1;
+pub const PERM_WRITE: i32 = 1 << 1;
+pub const PERM_READ: i32 = 1 << 2;
+
+///
+/// Topic的配置，创建和更新Topic的时候使用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TopicConfig {
+    #[serde(rename = "topicName")]
+    pub topic: String,
+    pub read_queue_nums: i32,
+    pub write_queue_nums: i32,
+    pub perm: i32,
+    pub topic_filter_type: String,
+    pub topic_sys_flag: i32,
+    pub order: bool,
+    ///
+    /// 没有建模的字段，例如: 5.x的attributes，只用于保留Broker返回的配置，创建和更新Topic的请求头中不包含这些字段
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for TopicConfig {
+    ///
+    /// 默认8个读写队列，可读可写，和mqadmin updateTopic的默认值一致
+    fn default() -> Self {
+        TopicConfig {
+            topic: String::new(),
+            read_queue_nums: 8,
+            write_queue_nums: 8,
+            perm: PERM_READ | PERM_WRITE,
+            topic_filter_type: String::from("SINGLE_TAG"),
+            topic_sys_flag: 0,
+            order: false,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl TopicConfig {
+    pub fn new(topic: String) -> TopicConfig {
+        TopicConfig {
+            topic,
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// Broker上所有Topic的配置
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TopicConfigTable {
+    #[serde(default)]
+    topic_config_table: HashMap<String, TopicConfig>,
+}
+
+///
+/// Broker上所有订阅组的配置
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscriptionGroupTable {
+    #[serde(default)]
+    subscription_group_table: HashMap<String, SubscriptionGroupConfig>,
+}
+
+///
+/// 按照时间重置消费位点的结果
+#[derive(Debug, Default)]
+pub struct ResetOffsetResult {
+    ///
+    /// 重置成功的每个队列的新offset
+    pub offsets: HashMap<MessageQueue, i64>,
+    ///
+    /// 没有Master或者重置失败的Broker的名字
+    pub failed_brokers: Vec<String>,
+}
+
+///
+/// 订阅组的配置，创建和更新订阅组的时候作为JSON格式的body发送
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SubscriptionGroupConfig {
+    pub group_name: String,
+    pub consume_enable: bool,
+    pub consume_from_min_enable: bool,
+    pub consume_broadcast_enable: bool,
+    pub consume_message_orderly: bool,
+    pub retry_queue_nums: i32,
+    pub retry_max_times: i32,
+    pub broker_id: i64,
+    pub which_broker_when_consume_slowly: i64,
+    pub notify_consumer_ids_changed_enable: bool,
+    ///
+    /// 没有建模的字段，例如: groupSysFlag、consumeTimeoutMinute以及5.x的attributes，
+    /// 从Broker获取的配置在更新的时候原样发送，不会被重置
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for SubscriptionGroupConfig {
+    ///
+    /// 和Broker的SubscriptionGroupConfig的默认值一致
+    fn default() -> Self {
+        SubscriptionGroupConfig {
+            group_name: String::new(),
+            consume_enable: true,
+            consume_from_min_enable: true,
+            consume_broadcast_enable: true,
+            consume_message_orderly: false,
+            retry_queue_nums: 1,
+            retry_max_times: 16,
+            broker_id: 0,
+            which_broker_when_consume_slowly: 1,
+            notify_consumer_ids_changed_enable: true,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl SubscriptionGroupConfig {
+    pub fn new(group_name: String) -> SubscriptionGroupConfig {
+        SubscriptionGroupConfig {
+            group_name,
+            ..Default::default()
+        }
+    }
+}
+
+impl Client {
+    ///
+    /// 在集群的每个Master Broker上创建或者更新Topic，Broker会把Topic注册到Nameserver；全部成功的时候返回true
+    pub async fn create_or_update_topic(&mut self, cluster: &str, config: &TopicConfig) -> bool {
+        let addrs = self.cluster_master_addrs(cluster).await;
+        if addrs.is_empty() {
+            return false;
+        }
+        let mut success = true;
+        for addr in addrs {
+            success &= self.create_or_update_topic_in_broker(&addr, config).await;
+        }
+        success
+    }
+
+    ///
+    /// 在指定的Broker上创建或者更新Topic
+    pub async fn create_or_update_topic_in_broker(
+        &mut self,
+        addr: &str,
+        config: &TopicConfig,
+    ) -> bool {
+        let custom_header = Some(CreateTopicRequestHeader::new(
+            config.topic.clone(),
+            config.read_queue_nums,
+            config.write_queue_nums,
+            config.perm,
+            config.topic_filter_type.clone(),
+            config.topic_sys_flag,
+            config.order,
+        ));
+        let command = RemotingCommand::build(RequestCode::UpdateAndCreateTopic, custom_header);
+        self.broker_request(addr, command).await.is_some()
+    }
+
+    ///
+    /// 先在集群的每个Master Broker上删除Topic，再从配置的每一个Nameserver删除Topic的路由；全部成功的时候返回true
+    pub async fn delete_topic(&mut self, cluster: &str, topic: String) -> bool {
+        let addrs = self.cluster_master_addrs(cluster).await;
+        if addrs.is_empty() {
+            return false;
+        }
+        let mut success = true;
+        for addr in addrs {
+            let custom_header = Some(DeleteTopicRequestHeader::new(topic.clone()));
+            let command = RemotingCommand::build(RequestCode::DeleteTopicInBroker, custom_header);
+            success &= self.broker_request(&addr, command).await.is_some();
+        }
+        success &= self
+            .all_namesrv_request(|| {
+                let custom_header = Some(DeleteTopicFromNamesrvRequestHeader::new(
+                    topic.clone(),
+                    cluster.to_string(),
+                ));
+                RemotingCommand::build(RequestCode::DeleteTopicInNamesrv, custom_header)
+            })
+            .await;
+        success
+    }
+
+    ///
+    /// 获取指定Broker上所有Topic的配置，key是Topic的名字
+    pub async fn topic_configs(&mut self, addr: &str) -> Option<HashMap<String, TopicConfig>> {
+        let command = RemotingCommand::new(RequestCode::GetAllTopicConfig);
+        let response = self.broker_request(addr, command).await?;
+        let json = Tokenizer::new(response.body().to_string()).regular_json();
+        let table: TopicConfigTable = serde_json::from_str(&json).ok()?;
+        Some(table.topic_config_table)
+    }
+
+    ///
+    /// 获取指定Broker上所有订阅组的配置，key是订阅组的名字
+    pub async fn subscription_groups(
+        &mut self,
+        addr: &str,
+    ) -> Option<HashMap<String, SubscriptionGroupConfig>> {
+        let command = RemotingCommand::new(RequestCode::GetAllSubscriptionGroupConfig);
+        let response = self.broker_request(addr, command).await?;
+        let json = Tokenizer::new(response.body().to_string()).regular_json();
+        let table: SubscriptionGroupTable = serde_json::from_str(&json).ok()?;
+        Some(table.subscription_group_table)
+    }
+
+    ///
+    /// 集群中所有Master Broker的地址，Nameserver请求失败的时候为空
+    pub async fn cluster_master_addrs(&mut self, cluster: &str) -> Vec<String> {
+        match self.broker_info().await {
+            Some(broker_info) => broker_info
+                .cluster_master_addrs(cluster)
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    ///
+    /// 把消费者组在Topic上所有队列的消费位点重置到timestamp对应的offset，时间单位是毫秒。
+    /// 在Topic所在的每个Master Broker上执行，Broker会通知在线的消费者客户端。
+    /// Topic的路由不存在的时候返回None
+    pub async fn reset_offset_by_timestamp(
+        &mut self,
+        topic: String,
+        group: String,
+        timestamp: i64,
+        force: bool,
+    ) -> Option<ResetOffsetResult> {
+        let route = self.topic_route(topic.clone()).await?;
+        let mut result = ResetOffsetResult::default();
+        for broker in route.brokers() {
+            let addr = match broker.master_broker_addrs() {
+                Some(addr) => addr.clone(),
+                None => {
+                    result.failed_brokers.push(broker.broker_name().to_string());
+                    continue;
+                }
+            };
+            let custom_header = Some(ResetOffsetRequestHeader::by_timestamp(
+                topic.clone(),
+                group.clone(),
+                timestamp,
+                force,
+            ));
+            let command =
+                RemotingCommand::build(RequestCode::InvokeBrokerToResetOffset, custom_header);
+            match self
+                .broker_request(&addr, command)
+                .await
+                .and_then(|response| ResetOffsetBody::parse(response.body().to_string()))
+            {
+                Some(body) => result.offsets.extend(body.offset_table().clone()),
+                None => result.failed_brokers.push(broker.broker_name().to_string()),
+            }
+        }
+        Some(result)
+    }
+
+    ///
+    /// 把消费者组在指定队列上的消费位点重置到offset。
+    /// 使用InvokeBrokerToResetOffset指定队列和offset，只有5.x的Broker支持；
+    /// 4.x的Broker忽略queueId和offset，会把整个Topic重置到timestamp=-1也就是最大offset，
+    /// 所以Broker的版本低于5.x或者无法获取版本的时候不发送请求，直接返回false
+    pub async fn reset_offset(&mut self, mq: &MessageQueue, group: String, offset: i64) -> bool {
+        let route = match self.topic_route(mq.topic().to_string()).await {
+            Some(route) => route,
+            None => return false,
+        };
+        let addr = match route
+            .brokers()
+            .iter()
+            .find(|broker| broker.broker_name() == mq.broker_name())
+            .and_then(|broker| broker.master_broker_addrs())
+        {
+            Some(addr) => addr.clone(),
+            None => return false,
+        };
+        let major_version = self.broker_runtime_info(&addr).await.and_then(|info| {
+            info.entries()
+                .get("brokerVersionDesc")
+                .and_then(|desc| broker_major_version(desc))
+        });
+        if major_version.is_none_or(|version| version < 5) {
+            return false;
+        }
+        let custom_header = Some(ResetOffsetRequestHeader::by_offset(
+            mq.topic().to_string(),
+            group,
+            mq.queue_id(),
+            offset,
+        ));
+        let command = RemotingCommand::build(RequestCode::InvokeBrokerToResetOffset, custom_header);
+        self.broker_request(&addr, command).await.is_some()
+    }
+
+    ///
+    /// 在集群的每个Master Broker上创建或者更新订阅组；全部成功的时候返回true
+    pub async fn create_or_update_subscription_group(
+        &mut self,
+        cluster: &str,
+        config: &SubscriptionGroupConfig,
+    ) -> bool {
+        let addrs = self.cluster_master_addrs(cluster).await;
+        if addrs.is_empty() {
+            return false;
+        }
+        let mut success = true;
+        for addr in addrs {
+            success &= self
+                .create_or_update_subscription_group_in_broker(&addr, config)
+                .await;
+        }
+        success
+    }
+
+    ///
+    /// 在指定的Broker上创建或者更新订阅组
+    pub async fn create_or_update_subscription_group_in_broker(
+        &mut self,
+        addr: &str,
+        config: &SubscriptionGroupConfig,
+    ) -> bool {
+        let body = Bytes::from(serde_json::to_vec(config).unwrap());
+        let command =
+            RemotingCommand::new(RequestCode::UpdateAndCreateSubscriptionGroup).with_body(body);
+        self.broker_request(addr, command).await.is_some()
+    }
+
+    ///
+    /// 在集群的每个Master Broker上删除订阅组，clean_offset为true的时候同时删除消费进度；全部成功的时候返回true
+    pub async fn delete_subscription_group(
+        &mut self,
+        cluster: &str,
+        group_name: String,
+        clean_offset: bool,
+    ) -> bool {
+        let addrs = self.cluster_master_addrs(cluster).await;
+        if addrs.is_empty() {
+            return false;
+        }
+        let mut success = true;
+        for addr in addrs {
+            let custom_header = Some(DeleteSubscriptionGroupRequestHeader::new(
+                group_name.clone(),
+                clean_offset,
+            ));
+            let command =
+                RemotingCommand::build(RequestCode::DeleteSuscriptionGroup, custom_header);
+            success &= self.broker_request(&addr, command).await.is_some();
+        }
+        success
+    }
+}
+
+///
+/// 从brokerVersionDesc中解析主版本号，例如: V4_9_2返回4
+fn broker_major_version(desc: &str) -> Option<u32> {
+    desc.strip_prefix('V')?.split('_').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "mock")]
+    use crate::remoting::mock::{cluster_info, MockResponse, MockServer};
+
+    ///
+    /// order_topic分布在broker-a和broker-b上的集群
+    #[cfg(feature = "mock")]
+    async fn cluster() -> (MockServer, MockServer, MockServer, Client) {
+        let broker_a = MockServer::start().await.unwrap();
+        let broker_b = MockServer::start().await.unwrap();
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker_a.addr())),
+        );
+        namesrv.on(
+            RequestCode::GetRouteInfoByTopic,
+            MockResponse::success(format!(
+                r#"{{"brokerDatas":[{{"brokerAddrs":{{0:"{}"}},"brokerName":"broker-a","cluster":"DefaultCluster"}},{{"brokerAddrs":{{0:"{}"}},"brokerName":"broker-b","cluster":"DefaultCluster"}}],"queueDatas":[{{"brokerName":"broker-a","perm":6,"readQueueNums":2,"topicSysFlag":0,"writeQueueNums":2}},{{"brokerName":"broker-b","perm":6,"readQueueNums":2,"topicSysFlag":0,"writeQueueNums":2}}]}}"#,
+                broker_a.addr(),
+                broker_b.addr()
+            )),
+        );
+        let client = Client::connection(namesrv.addr()).await.unwrap();
+        (namesrv, broker_a, broker_b, client)
+    }
+
+    #[test]
+    fn test_parse_topic_config_table() {
+        let json = r#"{"dataVersion":{"counter":3,"timestamp":1706162317012},"topicConfigTable":{"order_topic":{"order":false,"perm":6,"readQueueNums":4,"topicFilterType":"SINGLE_TAG","topicName":"order_topic","topicSysFlag":0,"writeQueueNums":4}}}"#;
+        let json = Tokenizer::new(json.to_string()).regular_json();
+        let table: TopicConfigTable = serde_json::from_str(&json).unwrap();
+        let config = &table.topic_config_table["order_topic"];
+        assert_eq!(config.read_queue_nums, 4);
+        assert_eq!(config.perm, PERM_READ | PERM_WRITE);
+    }
+
+    #[test]
+    fn test_subscription_group_config_json() {
+        let config = SubscriptionGroupConfig::new("order_group".to_string());
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["groupName"], "order_group");
+        assert_eq!(json["retryMaxTimes"], 16);
+        assert_eq!(json["whichBrokerWhenConsumeSlowly"], 1);
+
+        // Broker返回的没有建模的字段在更新的时候原样发送
+        let json = r#"{"subscriptionGroupTable":{"order_group":{"brokerId":0,"consumeBroadcastEnable":true,"consumeEnable":false,"consumeFromMinEnable":true,"consumeMessageOrderly":false,"consumeTimeoutMinute":15,"groupName":"order_group","groupSysFlag":0,"notifyConsumerIdsChangedEnable":true,"retryMaxTimes":3,"retryQueueNums":1,"whichBrokerWhenConsumeSlowly":1}}}"#;
+        let table: SubscriptionGroupTable = serde_json::from_str(json).unwrap();
+        let config = &table.subscription_group_table["order_group"];
+        assert!(!config.consume_enable);
+        assert_eq!(config.retry_max_times, 3);
+        let json = serde_json::to_value(config).unwrap();
+        assert_eq!(json["consumeTimeoutMinute"], 15);
+        assert_eq!(json["groupSysFlag"], 0);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_reset_offset_by_timestamp_reports_failed_brokers() {
+        let (_namesrv, broker_a, broker_b, mut client) = cluster().await;
+        broker_a.on(
+            RequestCode::InvokeBrokerToResetOffset,
+            MockResponse::success(
+                r#"{"offsetTable":{{"brokerName":"broker-a","queueId":0,"topic":"order_topic"}:100}}"#,
+            ),
+        );
+        broker_b.on(
+            RequestCode::InvokeBrokerToResetOffset,
+            MockResponse::error(1),
+        );
+        let result = client
+            .reset_offset_by_timestamp(
+                "order_topic".to_string(),
+                "order_group".to_string(),
+                1706162310000,
+                true,
+            )
+            .await
+            .unwrap();
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 0);
+        assert_eq!(result.offsets, HashMap::from([(mq, 100)]));
+        assert_eq!(result.failed_brokers, vec!["broker-b"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_reset_offset_requires_5x_broker() {
+        let (_namesrv, broker_a, broker_b, mut client) = cluster().await;
+        for broker in [&broker_a, &broker_b] {
+            broker.on(
+                RequestCode::InvokeBrokerToResetOffset,
+                MockResponse::success(""),
+            );
+        }
+        broker_a.on(
+            RequestCode::GetBrokerRuntimeInfo,
+            MockResponse::success(r#"{"table":{"brokerVersionDesc":"V4_9_2"}}"#),
+        );
+        broker_b.on(
+            RequestCode::GetBrokerRuntimeInfo,
+            MockResponse::success(r#"{"table":{"brokerVersionDesc":"V5_1_4"}}"#),
+        );
+
+        // 4.x的Broker会把整个Topic重置到最大offset，不发送请求
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 1);
+        assert!(
+            !client
+                .reset_offset(&mq, "order_group".to_string(), 42)
+                .await
+        );
+        assert!(broker_a
+            .requests(RequestCode::InvokeBrokerToResetOffset)
+            .is_empty());
+
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-b".to_string(), 1);
+        assert!(
+            client
+                .reset_offset(&mq, "order_group".to_string(), 42)
+                .await
+        );
+        let request = &broker_b.requests(RequestCode::InvokeBrokerToResetOffset)[0];
+        assert_eq!(request["queueId"], "1");
+        assert_eq!(request["offset"], "42");
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_delete_topic() {
+        let (namesrv, broker_a, _broker_b, client) = cluster().await;
+        let standby = MockServer::start().await.unwrap();
+        let mut client =
+            Client::connection(format!("{};{}", client.namesrv_addr(), standby.addr()))
+                .await
+                .unwrap();
+        broker_a.on(RequestCode::DeleteTopicInBroker, MockResponse::success(""));
+        namesrv.on(RequestCode::DeleteTopicInNamesrv, MockResponse::success(""));
+        standby.on(RequestCode::DeleteTopicInNamesrv, MockResponse::success(""));
+        assert!(
+            client
+                .delete_topic("DefaultCluster", "order_topic".to_string())
+                .await
+        );
+        assert_eq!(
+            broker_a.requests(RequestCode::DeleteTopicInBroker),
+            vec![HashMap::from([(
+                "topic".to_string(),
+                "order_topic".to_string()
+            )])]
+        );
+        let expected = vec![HashMap::from([
+            ("topic".to_string(), "order_topic".to_string()),
+            ("clusterName".to_string(), "DefaultCluster".to_string()),
+        ])];
+        assert_eq!(
+            namesrv.requests(RequestCode::DeleteTopicInNamesrv),
+            expected
+        );
+        assert_eq!(
+            standby.requests(RequestCode::DeleteTopicInNamesrv),
+            expected
+        );
+
+        // 任意一个Nameserver删除失败的时候返回false
+        standby.on(RequestCode::DeleteTopicInNamesrv, MockResponse::error(1));
+        assert!(
+            !client
+                .delete_topic("DefaultCluster", "order_topic".to_string())
+                .await
+        );
+    }
+
+    #[test]
+    fn test_broker_major_version() {
+        assert_eq!(broker_major_version("V4_9_2"), Some(4));
+        assert_eq!(broker_major_version("V5_1_4"), Some(5));
+        assert_eq!(broker_major_version("unknown"), None);
+    }
+}
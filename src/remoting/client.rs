@@ -1,77 +1,253 @@
-use std::{f32::consts::E, io::Error};
+use std::{collections::HashMap, io::Error, time::Duration};
 
-use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::net::TcpStream;
 
 use crate::{
+    cmd::message::{MessageExt, MessageId},
     cmd::{
         command::RemotingCommand,
         command::{
-            CustomHeader, GetTopicStatsInfoHeader, RequestCode, TopicConsumerByWhoHeader,
-            TopicRouteInfoRequestHeader,
+            GetConsumeStatsHeader, GetConsumerConnectionListHeader, GetConsumerRunningInfoHeader,
+            GetEarliestMsgStoretimeHeader, GetTopicStatsInfoHeader, QueryConsumeTimeSpanHeader,
+            RequestCode, TopicConsumerByWhoHeader, TopicRouteInfoRequestHeader,
+        },
+        command::{
+            GetEarliestMsgStoretimeResponseHeader, GetMaxOffsetRequestHeader,
+            GetMinOffsetRequestHeader, OffsetResponseHeader, QueryConsumerOffsetRequestHeader,
+            QueryMessageRequestHeader, SearchOffsetRequestHeader, ViewMessageRequestHeader,
         },
     },
-    remoting::response::ConsumerGroups,
+    remoting::response::{ConsumerGroups, MessageQueue},
 };
 
 use super::{
-    connection::Connection,
-    response::{BrokerInformation, TopicRouteInformation, TopicStats, Topics},
+    connection::{Connection, DEFAULT_TIMEOUT},
+    recording::Recorder,
+    response::{
+        BrokerInformation, BrokerRuntimeInfo, ConfigProperties, ConsumeStats, ConsumeTimeSpans,
+        ConsumerConnection, ConsumerRunningInfo, DelayOffsets, TopicRouteInformation, TopicStats,
+        Topics,
+    },
 };
 
+///
+/// RocketMQ管理接口的客户端，保持和Nameserver以及Broker的连接，连接出错或者请求超时以后丢弃，下一次请求的时候重新连接
 pub struct Client {
-    connection: Connection,
-    broker_connections: Vec<Connection>,
+    namesrv_addr: String,
+    namesrv_addrs: Vec<String>,
+    connection: Option<Connection>,
+    broker_connections: HashMap<String, Connection>,
+    recorder: Option<Recorder>,
+    timeout: Duration,
 }
 
 impl Client {
-    pub async fn connection<T: ToSocketAddrs>(addr: T) -> Result<Client, Error> {
-        let socket = TcpStream::connect(addr).await?;
+    ///
+    /// 连接Nameserver以及集群中所有的Broker，Nameserver无法连接或者无法获取集群信息的时候返回错误。
+    /// 多个Nameserver的地址用`;`分隔，例如: `10.0.0.1:9876;10.0.0.2:9876`，连接第一个能够连接的Nameserver。
+    /// 无法连接的Broker被跳过，第一次请求的时候重新连接
+    pub async fn connection<T: ToString>(addr: T) -> Result<Client, Error> {
+        Client::connect(addr.to_string(), None).await
+    }
+
+    ///
+    /// 和connection一样连接集群，同时把所有的请求和响应录制到recorder中，录制的文件可以通过`Replay`回放
+    pub async fn connection_with_recorder<T: ToString>(
+        addr: T,
+        recorder: Recorder,
+    ) -> Result<Client, Error> {
+        Client::connect(addr.to_string(), Some(recorder)).await
+    }
+
+    async fn connect(addr: String, recorder: Option<Recorder>) -> Result<Client, Error> {
+        let namesrv_addrs: Vec<String> = addr
+            .split(';')
+            .map(str::trim)
+            .filter(|addr| !addr.is_empty())
+            .map(String::from)
+            .collect();
+        let mut result = Err(Error::other(format!("no nameserver address in '{addr}'")));
+        for addr in namesrv_addrs.iter() {
+            result = TcpStream::connect(addr).await;
+            if result.is_ok() {
+                break;
+            }
+        }
+        let socket = result?;
+        let namesrv_addr = socket.peer_addr()?.to_string();
 
-        let mut connection = Connection::new(socket);
+        let mut connection = Connection::new(socket).with_recorder(&namesrv_addr, recorder.clone());
 
-        let broker_connection = Client::broker_connection(&mut connection).await;
+        let broker_connection = Client::broker_connection(&mut connection, &recorder).await?;
 
         Ok(Client {
-            connection,
+            namesrv_addr,
+            namesrv_addrs,
+            connection: Some(connection),
             broker_connections: broker_connection,
+            recorder,
+            timeout: DEFAULT_TIMEOUT,
         })
     }
 
-    pub async fn broker_connection(namesrv_connection: &mut Connection) -> Vec<Connection> {
+    ///
+    /// 设置每个请求等待响应的超时时间，默认是3秒
+    pub fn with_timeout(mut self, timeout: Duration) -> Client {
+        self.timeout = timeout;
+        self.connection = self
+            .connection
+            .map(|connection| connection.with_timeout(timeout));
+        self.broker_connections = self
+            .broker_connections
+            .into_iter()
+            .map(|(addr, connection)| (addr, connection.with_timeout(timeout)))
+            .collect();
+        self
+    }
+
+    async fn open(&self, addr: &str) -> Option<Connection> {
+        let socket = TcpStream::connect(addr).await.ok()?;
+        let connection = Connection::new(socket)
+            .with_recorder(addr, self.recorder.clone())
+            .with_timeout(self.timeout);
+        Some(connection)
+    }
+
+    async fn broker_connection(
+        namesrv_connection: &mut Connection,
+        recorder: &Option<Recorder>,
+    ) -> Result<HashMap<String, Connection>, Error> {
         let command = RemotingCommand::new(RequestCode::GetBrokerClusterInfo);
-        let broker_info = namesrv_connection.send_request(command).await.unwrap();
-        let broker_info = BrokerInformation::parse(broker_info.body().to_string());
+        let broker_info = namesrv_connection
+            .send_request(command)
+            .await
+            .map_err(Error::other)?;
+        if !broker_info.is_success() {
+            return Err(Error::other(format!(
+                "GetBrokerClusterInfo failed with code {}",
+                broker_info.code()
+            )));
+        }
+        let broker_info = BrokerInformation::parse(broker_info.body().to_string())
+            .ok_or_else(|| Error::other("GetBrokerClusterInfo returned an invalid body"))?;
         let all_broker_addrs = broker_info.all_broker_addrs();
 
-        let mut broker_connections = vec![];
+        let mut broker_connections = HashMap::new();
         for addr in all_broker_addrs.iter() {
-            let socket = TcpStream::connect(*addr).await.unwrap();
-            let connection = Connection::new(socket);
-            broker_connections.push(connection);
+            if let Ok(socket) = TcpStream::connect(*addr).await {
+                let connection = Connection::new(socket).with_recorder(addr, recorder.clone());
+                broker_connections.insert(addr.to_string(), connection);
+            }
+        }
+        Ok(broker_connections)
+    }
+
+    ///
+    /// 当前连接的Nameserver的地址
+    pub fn namesrv_addr(&self) -> &str {
+        &self.namesrv_addr
+    }
+
+    ///
+    /// 配置的所有Nameserver的地址
+    pub fn namesrv_addrs(&self) -> &[String] {
+        &self.namesrv_addrs
+    }
+
+    ///
+    /// 向指定的Broker发送请求，连接不存在的时候新建连接，连接异常的时候丢弃连接，等待下次重建
+    pub(super) async fn broker_request(
+        &mut self,
+        addr: &str,
+        command: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if !self.broker_connections.contains_key(addr) {
+            let connection = self.open(addr).await?;
+            self.broker_connections.insert(addr.to_string(), connection);
+        }
+        let conn = self.broker_connections.get_mut(addr)?;
+        match conn.send_request(command).await {
+            Ok(response) if response.is_success() => Some(response),
+            Ok(_) => None,
+            Err(_) => {
+                self.broker_connections.remove(addr);
+                None
+            }
+        }
+    }
+
+    ///
+    /// 向Nameserver发送请求，响应不是成功的时候返回None。和Broker一样，连接异常的时候丢弃连接，下一次请求的时候重新连接
+    pub(super) async fn namesrv_request(
+        &mut self,
+        command: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if self.connection.is_none() {
+            self.connection = Some(self.open(&self.namesrv_addr).await?);
+        }
+        let conn = self.connection.as_mut()?;
+        match conn.send_request(command).await {
+            Ok(response) if response.is_success() => Some(response),
+            Ok(_) => None,
+            Err(_) => {
+                self.connection = None;
+                None
+            }
+        }
+    }
+
+    ///
+    /// 向配置的每一个Nameserver发送request构造的请求，例如从所有的Nameserver删除Topic的路由；全部成功的时候返回true
+    #[cfg(feature = "admin")]
+    pub(super) async fn all_namesrv_request(
+        &mut self,
+        request: impl Fn() -> RemotingCommand,
+    ) -> bool {
+        let mut success = true;
+        for addr in self.namesrv_addrs.clone() {
+            let response = match self.open(&addr).await {
+                Some(mut connection) => connection.send_request(request()).await.ok(),
+                None => None,
+            };
+            success &= response.is_some_and(|response| response.is_success());
         }
-        broker_connections
+        success
     }
 
     ///
-    /// 发送获取broker的信息的命令
+    /// 发送获取broker的信息的命令，Nameserver请求失败的时候返回None
     ///
-    pub async fn broker_info(&mut self) -> BrokerInformation {
+    pub async fn broker_info(&mut self) -> Option<BrokerInformation> {
         let command = RemotingCommand::new(RequestCode::GetBrokerClusterInfo);
-        let data = self.connection.send_request(command).await.unwrap();
-        let body = data.body();
-        let b = BrokerInformation::parse(body.to_string());
-        b
+        let data = self.namesrv_request(command).await?;
+        BrokerInformation::parse(data.body().to_string())
     }
 
     ///
-    /// 从Nameserver这个地址获取到Topic信息列表
+    /// 从Nameserver这个地址获取到Topic信息列表，Nameserver请求失败的时候返回None
     ///
-    pub async fn topic_list(&mut self) -> Topics {
+    pub async fn topic_list(&mut self) -> Option<Topics> {
         let command = RemotingCommand::new(RequestCode::GetAllTopicListFromNameserver);
-        let data = self.connection.send_request(command).await.unwrap();
+        let data = self.namesrv_request(command).await?;
         Topics::parse(data.body().to_string())
     }
 
+    ///
+    /// 从Nameserver获取系统Topic列表，包括集群名和Broker名对应的Topic
+    pub async fn system_topic_list(&mut self) -> Option<Topics> {
+        let command = RemotingCommand::new(RequestCode::GetSystemTopicListFromNs);
+        let response = self.namesrv_request(command).await?;
+        Topics::parse(response.body().to_string())
+    }
+
+    ///
+    /// 从指定的Broker获取系统Topic列表
+    pub async fn system_topic_list_from_broker(&mut self, addr: &str) -> Option<Topics> {
+        let command = RemotingCommand::new(RequestCode::GetSystemTopicListFromBroker);
+        let response = self.broker_request(addr, command).await?;
+        Topics::parse(response.body().to_string())
+    }
+
     ///
     /// 获取Topic的Route信息
     ///
@@ -79,45 +255,257 @@ impl Client {
         let custom_header = TopicRouteInfoRequestHeader::new(topic);
         let custom_header = Some(custom_header);
         let command = RemotingCommand::build(RequestCode::GetRouteInfoByTopic, custom_header);
-        let data = self.connection.send_request(command).await.unwrap();
-        if data.is_success() {
-            Some(TopicRouteInformation::parse(data.body().to_string()))
-        } else {
-            None
-        }
+        let data = self.namesrv_request(command).await?;
+        TopicRouteInformation::parse(data.body().to_string())
     }
 
     ///
-    /// 获取Topic的stats统计信息
-    pub async fn topic_stats(&mut self, topic: String) -> Option<TopicStats> {
+    /// 获取Topic在指定Broker上的stats统计信息
+    pub async fn topic_stats(&mut self, addr: &str, topic: String) -> Option<TopicStats> {
         let custom_header = Some(GetTopicStatsInfoHeader::new(topic));
         let command = RemotingCommand::build(RequestCode::GetTOpicStatsInfo, custom_header);
-        let conn = self.broker_connections.get_mut(0).unwrap();
-        let response = conn.send_request(command).await.unwrap();
-        if response.is_success() {
-            Some(TopicStats::parse(response.body().to_string()))
-        } else {
-            None
-        }
+        let response = self.broker_request(addr, command).await?;
+        TopicStats::parse(response.body().to_string())
     }
 
     ///
-    /// 获取Topic被哪些消费者消费了
-    pub async fn query_topic_consume_by_who(&mut self, topic: String) -> Option<ConsumerGroups> {
+    /// 获取Topic在指定Broker上被哪些消费者消费了
+    pub async fn query_topic_consume_by_who(
+        &mut self,
+        addr: &str,
+        topic: String,
+    ) -> Option<ConsumerGroups> {
         let custom_header = Some(TopicConsumerByWhoHeader::new(topic));
         let command = RemotingCommand::build(RequestCode::QueryTopicConsumeByWho, custom_header);
-        let conn = self.broker_connections.get_mut(0).unwrap();
-        let response = conn.send_request(command).await.unwrap();
-        if response.is_success() {
-            Some(ConsumerGroups::parse(response.body().to_string()))
-        } else {
-            None
-        }
+        let response = self.broker_request(addr, command).await?;
+        ConsumerGroups::parse(response.body().to_string())
     }
 
-    pub async fn query_broker_runtime_info(&mut self) {
+    ///
+    /// 获取指定Broker的运行时信息
+    pub async fn broker_runtime_info(&mut self, addr: &str) -> Option<BrokerRuntimeInfo> {
         let command = RemotingCommand::new(RequestCode::GetBrokerRuntimeInfo);
-        let conn = self.broker_connections.get_mut(0).unwrap();
-        let response = conn.send_request(command).await.unwrap();
+        let response = self.broker_request(addr, command).await?;
+        BrokerRuntimeInfo::from(response.body().to_string())
+    }
+
+    ///
+    /// 获取指定Broker持久化的延迟消息投递进度
+    pub async fn delay_offsets(&mut self, addr: &str) -> Option<DelayOffsets> {
+        let command = RemotingCommand::new(RequestCode::GetAllDelayOffset);
+        let response = self.broker_request(addr, command).await?;
+        DelayOffsets::parse(response.body().to_string())
+    }
+
+    ///
+    /// 获取指定Broker的配置信息
+    pub async fn broker_config(&mut self, addr: &str) -> Option<ConfigProperties> {
+        let command = RemotingCommand::new(RequestCode::GetBrokerConfig);
+        let response = self.broker_request(addr, command).await?;
+        Some(ConfigProperties::parse(response.body()))
+    }
+
+    ///
+    /// 获取Nameserver的配置信息
+    pub async fn namesrv_config(&mut self) -> Option<ConfigProperties> {
+        let command = RemotingCommand::new(RequestCode::GetNamesrvConfig);
+        let response = self.namesrv_request(command).await?;
+        Some(ConfigProperties::parse(response.body()))
+    }
+
+    ///
+    /// 获取消费者组在指定Broker上的在线客户端连接
+    pub async fn consumer_connection(
+        &mut self,
+        addr: &str,
+        consumer_group: String,
+    ) -> Option<ConsumerConnection> {
+        let custom_header = Some(GetConsumerConnectionListHeader::new(consumer_group));
+        let command = RemotingCommand::build(RequestCode::GetConsumerConnectionList, custom_header);
+        let response = self.broker_request(addr, command).await?;
+        ConsumerConnection::parse(response.body().to_string())
+    }
+
+    ///
+    /// 通过Broker从消费者客户端获取运行时信息，jstack_enable为true的时候同时获取客户端的线程栈
+    pub async fn consumer_running_info(
+        &mut self,
+        addr: &str,
+        consumer_group: String,
+        client_id: String,
+        jstack_enable: bool,
+    ) -> Option<ConsumerRunningInfo> {
+        let custom_header = Some(GetConsumerRunningInfoHeader::new(
+            consumer_group,
+            client_id,
+            jstack_enable,
+        ));
+        let command = RemotingCommand::build(RequestCode::GetConsumerRunningInfo, custom_header);
+        let response = self.broker_request(addr, command).await?;
+        ConsumerRunningInfo::parse(response.body().to_string())
+    }
+
+    ///
+    /// 查询消费者组在指定Broker上每个队列的消费时间跨度，可以得到最早的未消费消息已经等待了多久
+    pub async fn query_consume_time_span(
+        &mut self,
+        addr: &str,
+        topic: String,
+        group: String,
+    ) -> Option<ConsumeTimeSpans> {
+        let custom_header = Some(QueryConsumeTimeSpanHeader::new(topic, group));
+        let command = RemotingCommand::build(RequestCode::QueryConsumeTimeSpan, custom_header);
+        let response = self.broker_request(addr, command).await?;
+        ConsumeTimeSpans::parse(response.body().to_string())
+    }
+
+    ///
+    /// 获取消费者组在指定Broker上对Topic的消费进度
+    pub async fn consume_stats(
+        &mut self,
+        addr: &str,
+        consumer_group: String,
+        topic: String,
+    ) -> Option<ConsumeStats> {
+        let custom_header = Some(GetConsumeStatsHeader::new(consumer_group, topic));
+        let command = RemotingCommand::build(RequestCode::GetConsumeStats, custom_header);
+        let response = self.broker_request(addr, command).await?;
+        ConsumeStats::parse(response.body().to_string())
+    }
+
+    ///
+    /// 获取队列中最早的消息的存储时间，单位是毫秒
+    pub async fn earliest_msg_store_time(
+        &mut self,
+        addr: &str,
+        topic: String,
+        queue_id: i32,
+    ) -> Option<i64> {
+        let custom_header = Some(GetEarliestMsgStoretimeHeader::new(topic, queue_id));
+        let command = RemotingCommand::build(RequestCode::GetEarliestMsgStoretime, custom_header);
+        let response = self.broker_request(addr, command).await?;
+        let header: GetEarliestMsgStoretimeResponseHeader = response.decode_custom_header().ok()?;
+        Some(header.timestamp())
+    }
+
+    ///
+    /// 根据Broker生成的offset消息ID查询消息，消息ID中包含了存储消息的Broker地址和CommitLog的物理offset
+    pub async fn view_message_by_id(&mut self, msg_id: &str) -> Option<MessageExt> {
+        let id = MessageId::parse(msg_id)?;
+        let custom_header = Some(ViewMessageRequestHeader::new(id.offset()));
+        let command = RemotingCommand::build(RequestCode::ViewMessageById, custom_header);
+        let addr = id.addr().to_string();
+        let response = self.broker_request(&addr, command).await?;
+        MessageExt::decode(&mut response.body_bytes().clone())
+    }
+
+    ///
+    /// 在Topic所在的每个Master Broker上根据Key查询时间范围内的消息，时间单位是毫秒
+    pub async fn query_message_by_key(
+        &mut self,
+        topic: String,
+        key: String,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> Vec<MessageExt> {
+        let mut messages = vec![];
+        let route = match self.topic_route(topic.clone()).await {
+            Some(route) => route,
+            None => return messages,
+        };
+        for broker in route.brokers() {
+            let addr = match broker.master_broker_addrs() {
+                Some(addr) => addr.clone(),
+                None => continue,
+            };
+            let custom_header = Some(QueryMessageRequestHeader::new(
+                topic.clone(),
+                key.clone(),
+                max_num,
+                begin_timestamp,
+                end_timestamp,
+            ));
+            let command = RemotingCommand::build(RequestCode::QueryMessage, custom_header);
+            if let Some(response) = self.broker_request(&addr, command).await {
+                messages.extend(MessageExt::decode_batch(response.body_bytes().clone()));
+            }
+        }
+        messages
+    }
+
+    ///
+    /// 通过Topic的路由找到队列所在的Master Broker的地址
+    async fn queue_broker_addr(&mut self, mq: &MessageQueue) -> Option<String> {
+        let route = self.topic_route(mq.topic().to_string()).await?;
+        route
+            .brokers()
+            .iter()
+            .find(|broker| broker.broker_name() == mq.broker_name())?
+            .master_broker_addrs()
+            .cloned()
+    }
+
+    ///
+    /// 向队列所在的Broker发送请求，从响应的extFields中解码offset
+    async fn queue_offset_request(
+        &mut self,
+        mq: &MessageQueue,
+        command: RemotingCommand,
+    ) -> Option<i64> {
+        let addr = self.queue_broker_addr(mq).await?;
+        let response = self.broker_request(&addr, command).await?;
+        let header: OffsetResponseHeader = response.decode_custom_header().ok()?;
+        Some(header.offset())
+    }
+
+    ///
+    /// 查询队列中存储时间在timestamp之后的第一条消息的offset，时间单位是毫秒
+    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: i64) -> Option<i64> {
+        let custom_header = Some(SearchOffsetRequestHeader::new(
+            mq.topic().to_string(),
+            mq.queue_id(),
+            timestamp,
+        ));
+        let command = RemotingCommand::build(RequestCode::SearchOffsetByTimestamp, custom_header);
+        self.queue_offset_request(mq, command).await
+    }
+
+    ///
+    /// 获取队列的最大offset，也就是下一条消息的offset
+    pub async fn max_offset(&mut self, mq: &MessageQueue) -> Option<i64> {
+        let custom_header = Some(GetMaxOffsetRequestHeader::new(
+            mq.topic().to_string(),
+            mq.queue_id(),
+        ));
+        let command = RemotingCommand::build(RequestCode::GetmaxOffset, custom_header);
+        self.queue_offset_request(mq, command).await
+    }
+
+    ///
+    /// 获取队列的最小offset，更早的消息已经被删除
+    pub async fn min_offset(&mut self, mq: &MessageQueue) -> Option<i64> {
+        let custom_header = Some(GetMinOffsetRequestHeader::new(
+            mq.topic().to_string(),
+            mq.queue_id(),
+        ));
+        let command = RemotingCommand::build(RequestCode::GetMinOffset, custom_header);
+        self.queue_offset_request(mq, command).await
+    }
+
+    ///
+    /// 查询消费者组在队列上提交的消费位点，消费者组没有提交过的时候返回None
+    pub async fn query_consumer_offset(
+        &mut self,
+        mq: &MessageQueue,
+        consumer_group: String,
+    ) -> Option<i64> {
+        let custom_header = Some(QueryConsumerOffsetRequestHeader::new(
+            consumer_group,
+            mq.topic().to_string(),
+            mq.queue_id(),
+        ));
+        let command = RemotingCommand::build(RequestCode::QueryConsumerOffset, custom_header);
+        self.queue_offset_request(mq, command).await
     }
 }
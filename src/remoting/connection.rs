@@ -1,33 +1,103 @@
+use std::time::Duration;
+
 use futures::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
 use crate::cmd::command::RemotingCommand;
 
+use super::recording::{RecordedCommand, Recorder};
+
+///
+/// 请求默认的超时时间，和mqadmin的默认值一致
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
 #[derive(Debug)]
 pub struct Connection {
     stream: Framed<TcpStream, LengthDelimitedCodec>,
+    addr: String,
+    recorder: Option<Recorder>,
+    timeout: Duration,
 }
 
 impl Connection {
     pub fn new(socket: TcpStream) -> Connection {
+        let addr = socket
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
         Connection {
             stream: Framed::new(socket, LengthDelimitedCodec::new()),
+            addr,
+            recorder: None,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
 
+    ///
+    /// 把这个连接上的请求和响应录制到recorder中，addr是录制的地址
+    pub fn with_recorder(mut self, addr: &str, recorder: Option<Recorder>) -> Connection {
+        self.addr = addr.to_string();
+        self.recorder = recorder;
+        self
+    }
+
+    ///
+    /// 设置每个请求等待响应的超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Connection {
+        self.timeout = timeout;
+        self
+    }
+
+    ///
+    /// 发送请求并等待opaque相同的响应。发送失败、连接断开、响应的帧无法解析或者超时的时候返回错误，
+    /// 这时连接上可能还有没有读取的响应，调用方需要丢弃这个连接
     pub async fn send_request(
         &mut self,
         command: RemotingCommand,
     ) -> Result<RemotingCommand, String> {
+        let opaque = command.opaque();
+        let request = self
+            .recorder
+            .as_ref()
+            .map(|_| RecordedCommand::from(&command));
         let command = command.encode_no_length();
-        let _result = self.stream.send(command.freeze()).await;
+        self.stream
+            .send(command.freeze())
+            .await
+            .map_err(|e| format!("Send request to {} error: {e}", self.addr))?;
+
+        let response = tokio::time::timeout(self.timeout, self.read_response(opaque))
+            .await
+            .map_err(|_| {
+                format!(
+                    "Request to {} timed out after {}ms",
+                    self.addr,
+                    self.timeout.as_millis()
+                )
+            })??;
+        if let (Some(recorder), Some(request)) = (&self.recorder, request) {
+            recorder
+                .record(&self.addr, request, &response)
+                .map_err(|e| format!("Record exchange with {} error: {e}", self.addr))?;
+        }
+        Ok(response)
+    }
 
-        if let Some(Ok(data)) = self.stream.next().await {
-            println!("Get data from server:{:?}", data);
-            let response = RemotingCommand::parse(&data);
-            return Ok(response);
+    ///
+    /// 读取opaque相同的响应，跳过之前的请求遗留的响应
+    async fn read_response(&mut self, opaque: i32) -> Result<RemotingCommand, String> {
+        loop {
+            let data = match self.stream.next().await {
+                Some(Ok(data)) => data,
+                Some(Err(e)) => return Err(format!("Read frame from {} error: {e}", self.addr)),
+                None => return Err(format!("Connection to {} closed", self.addr)),
+            };
+            let response = RemotingCommand::parse(&data)
+                .ok_or_else(|| format!("Parse frame from {} error!", self.addr))?;
+            if response.opaque() == opaque {
+                return Ok(response);
+            }
         }
-        return Err(String::from("Parse frame error!"));
     }
 }
@@ -0,0 +1,488 @@
+//!
+//! 进程内模拟的Nameserver和Broker，用来在没有RocketMQ集群的机器上测试Client、Collector以及HTTP输出。
+//! 每个RequestCode可以预设响应、延迟、错误码或者断开连接，没有预设的请求返回RequestCodeNotSupported
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tokio_util::codec::Framed;
+
+use crate::cmd::command::{CommandCoderc, RemotingCommand, RequestCode, ResponseCode};
+
+///
+/// 模拟服务对一个请求的处理方式
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    code: i32,
+    body: Bytes,
+    ext_fields: HashMap<String, String>,
+    remark: Option<String>,
+    version: Option<i32>,
+    delay: Duration,
+    disconnect: bool,
+}
+
+impl MockResponse {
+    ///
+    /// 成功的响应，body一般是Broker返回的JSON
+    pub fn success(body: impl Into<Bytes>) -> MockResponse {
+        MockResponse {
+            code: ResponseCode::Success.code(),
+            body: body.into(),
+            ext_fields: HashMap::new(),
+            remark: None,
+            version: None,
+            delay: Duration::ZERO,
+            disconnect: false,
+        }
+    }
+
+    ///
+    /// 返回错误码，例如: 17表示Topic不存在
+    pub fn error(code: i32) -> MockResponse {
+        MockResponse {
+            code,
+            ..MockResponse::success(Bytes::new())
+        }
+    }
+
+    ///
+    /// 收到请求以后不响应，直接断开连接
+    pub fn disconnect() -> MockResponse {
+        MockResponse {
+            disconnect: true,
+            ..MockResponse::success(Bytes::new())
+        }
+    }
+
+    ///
+    /// 设置响应的body，例如错误响应中Broker返回的说明
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> MockResponse {
+        self.body = body.into();
+        self
+    }
+
+    ///
+    /// 在extFields中返回结果，例如GetMaxOffset的offset
+    pub fn with_ext_field(mut self, key: &str, value: &str) -> MockResponse {
+        self.ext_fields.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_ext_fields(mut self, ext_fields: HashMap<String, String>) -> MockResponse {
+        self.ext_fields = ext_fields;
+        self
+    }
+
+    ///
+    /// 响应的remark，例如错误响应中的原因
+    pub fn with_remark(mut self, remark: &str) -> MockResponse {
+        self.remark = Some(remark.to_string());
+        self
+    }
+
+    ///
+    /// 响应头部中的version，没有设置的时候使用默认的版本
+    pub fn with_version(mut self, version: i32) -> MockResponse {
+        self.version = Some(version);
+        self
+    }
+
+    ///
+    /// 等待delay以后再响应或者断开连接
+    pub fn with_delay(mut self, delay: Duration) -> MockResponse {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    ///
+    /// 每次都使用的响应，key是RequestCode
+    responses: HashMap<i32, MockResponse>,
+    ///
+    /// 只使用一次的响应，优先于responses
+    once: HashMap<i32, VecDeque<MockResponse>>,
+    ///
+    /// extFields完全一致的时候使用的响应，优先于responses
+    matched: Vec<(i32, HashMap<String, String>, VecDeque<MockResponse>)>,
+    ///
+    /// 收到的请求的RequestCode和extFields
+    requests: Vec<(i32, HashMap<String, String>)>,
+}
+
+impl Script {
+    fn next_response(&mut self, code: i32, ext_fields: &HashMap<String, String>) -> MockResponse {
+        if let Some(response) = self.once.get_mut(&code).and_then(|q| q.pop_front()) {
+            return response;
+        }
+        if let Some((_, _, responses)) = self
+            .matched
+            .iter_mut()
+            .find(|(c, fields, _)| *c == code && fields == ext_fields)
+        {
+            if responses.len() > 1 {
+                return responses.pop_front().unwrap();
+            }
+            return responses[0].clone();
+        }
+        self.responses
+            .get(&code)
+            .cloned()
+            .unwrap_or_else(|| MockResponse::error(ResponseCode::RequestCodeNotSupported.code()))
+    }
+}
+
+///
+/// 监听在127.0.0.1随机端口上的模拟服务，既可以作为Nameserver也可以作为Broker，drop的时候停止监听
+pub struct MockServer {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start() -> std::io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let script = Arc::new(Mutex::new(Script::default()));
+        let accept_script = script.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(Self::handle_connection(socket, accept_script.clone()));
+            }
+        });
+        Ok(MockServer {
+            addr,
+            script,
+            handle,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    ///
+    /// 设置RequestCode的响应，之后的每次请求都使用这个响应
+    pub fn on(&self, code: RequestCode, response: MockResponse) -> &MockServer {
+        self.on_code(code.code(), response)
+    }
+
+    pub(super) fn on_code(&self, code: i32, response: MockResponse) -> &MockServer {
+        self.script.lock().unwrap().responses.insert(code, response);
+        self
+    }
+
+    ///
+    /// 追加extFields和请求完全一致的时候使用的响应，多次追加的时候按照顺序使用，最后一个响应一直使用。
+    /// 例如: 不同Topic的GetTopicStatsInfo返回不同的结果
+    pub fn when(
+        &self,
+        code: RequestCode,
+        ext_fields: HashMap<String, String>,
+        response: MockResponse,
+    ) -> &MockServer {
+        self.when_code(code.code(), ext_fields, response)
+    }
+
+    pub(super) fn when_code(
+        &self,
+        code: i32,
+        ext_fields: HashMap<String, String>,
+        response: MockResponse,
+    ) -> &MockServer {
+        let mut script = self.script.lock().unwrap();
+        match script
+            .matched
+            .iter_mut()
+            .find(|(c, fields, _)| *c == code && *fields == ext_fields)
+        {
+            Some((_, _, responses)) => responses.push_back(response),
+            None => script
+                .matched
+                .push((code, ext_fields, VecDeque::from([response]))),
+        }
+        self
+    }
+
+    ///
+    /// 追加只使用一次的响应，按照追加的顺序使用，用完以后使用on设置的响应
+    pub fn once(&self, code: RequestCode, response: MockResponse) -> &MockServer {
+        self.script
+            .lock()
+            .unwrap()
+            .once
+            .entry(code.code())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    ///
+    /// 收到的RequestCode的所有请求的extFields，按照收到的顺序排列
+    pub fn requests(&self, code: RequestCode) -> Vec<HashMap<String, String>> {
+        let code = code.code();
+        self.script
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|(c, _)| *c == code)
+            .map(|(_, ext_fields)| ext_fields.clone())
+            .collect()
+    }
+
+    async fn handle_connection(socket: TcpStream, script: Arc<Mutex<Script>>) {
+        let mut stream = Framed::new(socket, CommandCoderc::default());
+        while let Some(Ok(request)) = stream.next().await {
+            let response = {
+                let mut script = script.lock().unwrap();
+                script
+                    .requests
+                    .push((request.code(), request.ext_fields().clone()));
+                script.next_response(request.code(), request.ext_fields())
+            };
+            if !response.delay.is_zero() {
+                tokio::time::sleep(response.delay).await;
+            }
+            if response.disconnect {
+                return;
+            }
+            let mut command = RemotingCommand::response(response.code, request.opaque())
+                .with_ext_fields(response.ext_fields)
+                .with_remark(response.remark)
+                .with_body(response.body);
+            if let Some(version) = response.version {
+                command = command.with_version(version);
+            }
+            if stream.send(command).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+///
+/// 只有一个Master Broker的集群信息，作为GetBrokerClusterInfo的响应
+pub fn cluster_info(cluster: &str, broker_name: &str, broker_addr: &SocketAddr) -> String {
+    format!(
+        r#"{{"brokerAddrTable":{{"{broker_name}":{{"brokerAddrs":{{0:"{broker_addr}"}},"brokerName":"{broker_name}","cluster":"{cluster}"}}}},"clusterAddrTable":{{"{cluster}":["{broker_name}"]}}}}"#
+    )
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{remoting::response::MessageQueue, Client};
+
+    async fn cluster() -> (MockServer, MockServer, Client) {
+        let broker = MockServer::start().await.unwrap();
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker.addr())),
+        );
+        namesrv.on(
+            RequestCode::GetRouteInfoByTopic,
+            MockResponse::success(format!(
+                r#"{{"brokerDatas":[{{"brokerAddrs":{{0:"{}"}},"brokerName":"broker-a","cluster":"DefaultCluster"}}],"queueDatas":[{{"brokerName":"broker-a","perm":6,"readQueueNums":4,"topicSysFlag":0,"writeQueueNums":4}}]}}"#,
+                broker.addr()
+            )),
+        );
+        let client = Client::connection(namesrv.addr()).await.unwrap();
+        (namesrv, broker, client)
+    }
+
+    #[tokio::test]
+    async fn test_client_against_mock_server() {
+        let (namesrv, broker, mut client) = cluster().await;
+        let broker_info = client.broker_info().await.unwrap();
+        assert_eq!(
+            broker_info.all_broker_addrs(),
+            vec![&broker.addr().to_string()]
+        );
+
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 1);
+        broker.on(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "42"),
+        );
+        assert_eq!(client.max_offset(&mq).await, Some(42));
+        assert_eq!(
+            broker.requests(RequestCode::GetmaxOffset)[0]["queueId"],
+            "1"
+        );
+        assert_eq!(namesrv.requests(RequestCode::GetRouteInfoByTopic).len(), 1);
+
+        // 错误码和缺少extFields的响应都没有结果
+        broker.once(RequestCode::GetMinOffset, MockResponse::error(17));
+        assert_eq!(client.min_offset(&mq).await, None);
+        broker.once(
+            RequestCode::GetMinOffset,
+            MockResponse::success(Bytes::new()),
+        );
+        assert_eq!(client.min_offset(&mq).await, None);
+        assert_eq!(client.search_offset(&mq, 0).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_queue_offsets() {
+        let (_namesrv, broker, mut client) = cluster().await;
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 2);
+        broker.on(
+            RequestCode::SearchOffsetByTimestamp,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "30"),
+        );
+        broker.on(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "100"),
+        );
+        broker.on(
+            RequestCode::GetMinOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "10"),
+        );
+        assert_eq!(client.search_offset(&mq, 1706162310000).await, Some(30));
+        assert_eq!(client.max_offset(&mq).await, Some(100));
+        assert_eq!(client.min_offset(&mq).await, Some(10));
+
+        let expected = HashMap::from([
+            ("topic".to_string(), "order_topic".to_string()),
+            ("queueId".to_string(), "2".to_string()),
+        ]);
+        let mut search = expected.clone();
+        search.insert("timestamp".to_string(), "1706162310000".to_string());
+        assert_eq!(
+            broker.requests(RequestCode::SearchOffsetByTimestamp),
+            vec![search]
+        );
+        assert_eq!(
+            broker.requests(RequestCode::GetmaxOffset),
+            vec![expected.clone()]
+        );
+        assert_eq!(broker.requests(RequestCode::GetMinOffset), vec![expected]);
+
+        // offset不是数字的响应没有结果
+        broker.once(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "x"),
+        );
+        assert_eq!(client.max_offset(&mq).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_nameserver_failures() {
+        // Nameserver返回错误码的时候连接失败
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(RequestCode::GetBrokerClusterInfo, MockResponse::error(1));
+        assert!(Client::connection(namesrv.addr()).await.is_err());
+
+        // 无法连接的Broker被跳过
+        let unreachable = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        namesrv.on(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::success(cluster_info("DefaultCluster", "broker-a", &unreachable)),
+        );
+        let mut client = Client::connection(namesrv.addr()).await.unwrap();
+
+        namesrv.on(
+            RequestCode::GetAllTopicListFromNameserver,
+            MockResponse::error(1),
+        );
+        assert!(client.topic_list().await.is_none());
+        assert!(client
+            .topic_route("order_topic".to_string())
+            .await
+            .is_none());
+        namesrv.once(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::disconnect(),
+        );
+        assert!(client.broker_info().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delay_and_disconnect() {
+        let (_namesrv, broker, mut client) = cluster().await;
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 0);
+        broker.on(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "7"),
+        );
+        broker.once(RequestCode::GetmaxOffset, MockResponse::disconnect());
+        broker.once(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new())
+                .with_ext_field("offset", "5")
+                .with_delay(Duration::from_millis(50)),
+        );
+
+        // 断开的连接被丢弃，下一次请求重新连接
+        assert_eq!(client.max_offset(&mq).await, None);
+        let start = Instant::now();
+        assert_eq!(client.max_offset(&mq).await, Some(5));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(client.max_offset(&mq).await, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let (namesrv, broker, client) = cluster().await;
+        let mut client = client.with_timeout(Duration::from_millis(100));
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 0);
+        broker.on(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new()).with_ext_field("offset", "7"),
+        );
+        broker.once(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new())
+                .with_ext_field("offset", "5")
+                .with_delay(Duration::from_millis(500)),
+        );
+
+        // 超时的请求返回None，连接被丢弃，迟到的响应不会被下一次请求读到
+        let start = Instant::now();
+        assert_eq!(client.max_offset(&mq).await, None);
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(client.max_offset(&mq).await, Some(7));
+
+        // Nameserver的连接断开以后重新连接
+        namesrv.on(
+            RequestCode::GetAllTopicListFromNameserver,
+            MockResponse::success(r#"{"topicList":["order_topic"]}"#),
+        );
+        namesrv.once(
+            RequestCode::GetAllTopicListFromNameserver,
+            MockResponse::disconnect(),
+        );
+        assert!(client.topic_list().await.is_none());
+        assert_eq!(
+            client.topic_list().await.unwrap().topics(),
+            &vec!["order_topic".to_string()]
+        );
+    }
+}
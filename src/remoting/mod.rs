@@ -1,3 +1,13 @@
+//!
+//! 和Nameserver、Broker通信的客户端，以及响应body解析以后的类型
+
+#[cfg(feature = "admin")]
+pub mod admin;
 pub mod client;
 pub mod connection;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod recording;
+#[cfg(feature = "mock")]
+pub mod replay;
 pub mod response;
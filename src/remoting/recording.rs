@@ -0,0 +1,221 @@
+//!
+//! 录制真实集群的请求和响应。`Recorder`把每一次请求和响应以JSON Lines格式追加到文件中，
+//! `Recording`读取录制的文件，开启`mock` feature的时候可以通过`replay::Replay`回放
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::command::{RemotingCommand, RequestCode};
+
+///
+/// 录制的一次请求和响应，addr是Client使用的地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    addr: String,
+    request: RecordedCommand,
+    response: RecordedCommand,
+}
+
+impl Exchange {
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn request(&self) -> &RecordedCommand {
+        &self.request
+    }
+
+    pub fn response(&self) -> &RecordedCommand {
+        &self.response
+    }
+}
+
+///
+/// 录制的RemotingCommand，body是UTF-8的时候保存为字符串，否则保存为十六进制的bodyHex。
+/// 头部中除了opaque以外的字段都会保存，旧的录制中没有的字段为空
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedCommand {
+    code: i32,
+    #[serde(default)]
+    flag: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remark: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ext_fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_hex: Option<String>,
+}
+
+impl RecordedCommand {
+    pub fn from(command: &RemotingCommand) -> RecordedCommand {
+        let bytes = command.body_bytes();
+        let (body, body_hex) = if bytes.is_empty() {
+            (None, None)
+        } else {
+            match std::str::from_utf8(bytes) {
+                Ok(body) => (Some(body.to_string()), None),
+                Err(_) => (None, Some(encode_hex(bytes))),
+            }
+        };
+        let header = command.header();
+        RecordedCommand {
+            code: command.code(),
+            flag: header.flag(),
+            language: Some(header.language().to_string()),
+            version: Some(header.version()),
+            remark: header.remark().map(|remark| remark.to_string()),
+            ext_fields: command
+                .ext_fields()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            body,
+            body_hex,
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    pub fn flag(&self) -> i32 {
+        self.flag
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    ///
+    /// 响应的remark，Broker在错误的响应中说明原因
+    pub fn remark(&self) -> Option<&str> {
+        self.remark.as_deref()
+    }
+
+    pub fn ext_fields(&self) -> &BTreeMap<String, String> {
+        &self.ext_fields
+    }
+
+    ///
+    /// 文本格式的body，二进制的body返回None
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    ///
+    /// 原始的body，bodyHex不合法的时候为空
+    pub fn body_bytes(&self) -> Bytes {
+        match (&self.body, &self.body_hex) {
+            (Some(body), _) => Bytes::from(body.clone()),
+            (None, Some(hex)) => decode_hex(hex).map(Bytes::from).unwrap_or_default(),
+            (None, None) => Bytes::new(),
+        }
+    }
+}
+
+///
+/// 把请求和响应追加到文件中，每一行是一个Exchange，多个连接共享同一个文件
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    ///
+    /// 创建录制文件，文件已经存在的时候清空
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        Ok(Recorder {
+            file: Arc::new(Mutex::new(File::create(path)?)),
+        })
+    }
+
+    ///
+    /// 追加一次请求和响应，写文件失败的时候返回错误
+    pub fn record(
+        &self,
+        addr: &str,
+        request: RecordedCommand,
+        response: &RemotingCommand,
+    ) -> io::Result<()> {
+        let exchange = Exchange {
+            addr: addr.to_string(),
+            request,
+            response: RecordedCommand::from(response),
+        };
+        let mut line = serde_json::to_string(&exchange)?;
+        line.push('\n');
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+}
+
+///
+/// 从文件中读取的录制
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    exchanges: Vec<Exchange>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        let source = std::fs::read_to_string(path)?;
+        Recording::parse(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    ///
+    /// 解析JSON Lines格式的录制，忽略空行
+    pub fn parse(source: &str) -> Result<Recording, serde_json::Error> {
+        let exchanges = source
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Exchange>, _>>()?;
+        Ok(Recording { exchanges })
+    }
+
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    ///
+    /// Nameserver的地址，GetBrokerClusterInfo只会发送给Nameserver
+    pub fn namesrv_addr(&self) -> Option<&str> {
+        let code = RequestCode::GetBrokerClusterInfo.code();
+        self.exchanges
+            .iter()
+            .find(|e| e.request.code == code)
+            .map(|e| e.addr.as_str())
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,398 @@
+//!
+//! 回放`recording::Recorder`录制的请求和响应。为录制中的每个地址启动一个`MockServer`，按照RequestCode和extFields返回录制的响应，
+//! 可以把生产环境中不同版本Broker返回的响应变成可以重复执行的回归测试。只有开启`mock` feature的时候才会编译
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io,
+    net::SocketAddr,
+};
+
+use bytes::Bytes;
+use regex::Regex;
+
+use crate::cmd::command::{RequestCode, ResponseCode};
+
+use super::{
+    mock::{MockResponse, MockServer},
+    recording::Recording,
+    response::BrokerInformation,
+};
+
+///
+/// 回放录制的模拟集群。Broker的地址被替换为本地MockServer的地址，extFields和录制一致的请求按照录制的顺序返回响应，
+/// 其他的请求返回同一个RequestCode最后录制的响应
+pub struct Replay {
+    namesrv_addr: SocketAddr,
+    servers: BTreeMap<String, MockServer>,
+}
+
+impl Replay {
+    pub async fn start(recording: &Recording) -> io::Result<Replay> {
+        let namesrv = recording.namesrv_addr().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "recording has no GetBrokerClusterInfo request",
+            )
+        })?;
+
+        // Client连接集群中所有的Master Broker，包括录制中没有请求的Broker
+        let cluster_info = RequestCode::GetBrokerClusterInfo.code();
+        let mut addrs: BTreeSet<String> = BTreeSet::new();
+        for exchange in recording.exchanges().iter() {
+            addrs.insert(exchange.addr().to_string());
+            if exchange.request().code() == cluster_info
+                && exchange.response().code() == ResponseCode::Success.code()
+            {
+                if let Some(broker_info) = exchange
+                    .response()
+                    .body()
+                    .and_then(|body| BrokerInformation::parse(body.to_string()))
+                {
+                    addrs.extend(broker_info.all_broker_addrs().into_iter().cloned());
+                }
+            }
+        }
+
+        let mut servers = BTreeMap::new();
+        let mut replacements = HashMap::new();
+        for addr in addrs {
+            let server = MockServer::start().await?;
+            replacements.insert(addr.clone(), server.addr().to_string());
+            servers.insert(addr, server);
+        }
+        let rewrite = AddrRewrite::new(replacements);
+
+        for exchange in recording.exchanges().iter() {
+            let response = exchange.response();
+            let body = match response.body() {
+                Some(body) => Bytes::from(rewrite.apply(body)),
+                None => response.body_bytes(),
+            };
+            let mock = if response.code() == ResponseCode::Success.code() {
+                MockResponse::success(body)
+            } else {
+                MockResponse::error(response.code()).with_body(body)
+            }
+            .with_ext_fields(response.ext_fields().clone().into_iter().collect());
+            let mock = match response.remark() {
+                Some(remark) => mock.with_remark(remark),
+                None => mock,
+            };
+            let mock = match response.version() {
+                Some(version) => mock.with_version(version),
+                None => mock,
+            };
+            let server = &servers[exchange.addr()];
+            let code = exchange.request().code();
+            let ext_fields = exchange
+                .request()
+                .ext_fields()
+                .clone()
+                .into_iter()
+                .collect();
+            server.when_code(code, ext_fields, mock.clone());
+            server.on_code(code, mock);
+        }
+
+        Ok(Replay {
+            namesrv_addr: servers[namesrv].addr(),
+            servers,
+        })
+    }
+
+    ///
+    /// 回放的Nameserver的地址，Client连接这个地址
+    pub fn namesrv_addr(&self) -> SocketAddr {
+        self.namesrv_addr
+    }
+
+    ///
+    /// 录制中的地址对应的MockServer，可以在回放以后继续预设响应或者检查收到的请求
+    pub fn server(&self, recorded_addr: &str) -> Option<&MockServer> {
+        self.servers.get(recorded_addr)
+    }
+}
+
+///
+/// 一次性替换body中所有录制的地址，避免替换以后的地址被再次替换。
+/// 前面是数字或者`.`、后面是数字的地址不替换，例如: 10.0.0.1:10911不匹配110.0.0.1:10911
+struct AddrRewrite {
+    pattern: Option<Regex>,
+    replacements: HashMap<String, String>,
+}
+
+impl AddrRewrite {
+    fn new(replacements: HashMap<String, String>) -> AddrRewrite {
+        let mut addrs: Vec<&String> = replacements.keys().collect();
+        // 较长的地址优先匹配，例如: 10.0.0.1:10911优先于10.0.0.1:1091
+        addrs.sort_by_key(|addr| std::cmp::Reverse(addr.len()));
+        let pattern = if addrs.is_empty() {
+            None
+        } else {
+            let alternation: Vec<String> = addrs.iter().map(|addr| regex::escape(addr)).collect();
+            Some(Regex::new(&alternation.join("|")).unwrap())
+        };
+        AddrRewrite {
+            pattern,
+            replacements,
+        }
+    }
+
+    fn apply(&self, body: &str) -> String {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern,
+            None => return body.to_string(),
+        };
+        let mut result = String::with_capacity(body.len());
+        let mut last = 0;
+        for m in pattern.find_iter(body) {
+            let before = body[..m.start()].chars().next_back();
+            let after = body[m.end()..].chars().next();
+            if before.is_some_and(|c| c.is_ascii_digit() || c == '.')
+                || after.is_some_and(|c| c.is_ascii_digit())
+            {
+                continue;
+            }
+            result.push_str(&body[last..m.start()]);
+            result.push_str(&self.replacements[m.as_str()]);
+            last = m.end();
+        }
+        result.push_str(&body[last..]);
+        result
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use bytes::Bytes;
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{
+        cmd::command::{GetTopicStatsInfoHeader, RemotingCommand},
+        remoting::{
+            connection::Connection,
+            mock::cluster_info,
+            recording::Recorder,
+            response::{MessageQueue, TopicStats},
+        },
+        Client,
+    };
+
+    fn topic_stats(topic: &str, max_offset: i64) -> String {
+        format!(
+            r#"{{"offsetTable":{{{{"brokerName":"broker-a","queueId":0,"topic":"{topic}"}}:{{"lastUpdateTimestamp":0,"maxOffset":{max_offset},"minOffset":0}}}}}}"#
+        )
+    }
+
+    fn max_offsets(stats: &TopicStats) -> Vec<i64> {
+        stats
+            .offset_table()
+            .values()
+            .map(|o| o.max_offset())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let broker = MockServer::start().await.unwrap();
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(
+            RequestCode::GetBrokerClusterInfo,
+            MockResponse::success(cluster_info("DefaultCluster", "broker-a", &broker.addr())),
+        );
+        namesrv.on(
+            RequestCode::GetRouteInfoByTopic,
+            MockResponse::success(format!(
+                r#"{{"brokerDatas":[{{"brokerAddrs":{{0:"{}"}},"brokerName":"broker-a","cluster":"DefaultCluster"}}],"queueDatas":[]}}"#,
+                broker.addr()
+            )),
+        );
+        for (topic, max_offset) in [("order_topic", 120), ("pay_topic", 7)] {
+            broker.when(
+                RequestCode::GetTOpicStatsInfo,
+                HashMap::from([("topic".to_string(), topic.to_string())]),
+                MockResponse::success(topic_stats(topic, max_offset)),
+            );
+        }
+        broker.on(
+            RequestCode::GetmaxOffset,
+            MockResponse::success(Bytes::new())
+                .with_ext_field("offset", "42")
+                .with_version(397),
+        );
+        broker.on(
+            RequestCode::GetMinOffset,
+            MockResponse::error(17).with_remark("topic not exist"),
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "rocketmq-exporter-recording-{}.jsonl",
+            std::process::id()
+        ));
+        let recorder = Recorder::create(&path).unwrap();
+        let mut client = Client::connection_with_recorder(namesrv.addr(), recorder)
+            .await
+            .unwrap();
+        let broker_addr = broker.addr().to_string();
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 0);
+        client
+            .topic_stats(&broker_addr, "order_topic".to_string())
+            .await;
+        client
+            .topic_stats(&broker_addr, "pay_topic".to_string())
+            .await;
+        client.max_offset(&mq).await;
+        client.min_offset(&mq).await;
+        drop((client, broker, namesrv));
+
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.exchanges().len(), 7);
+        let response = |code: RequestCode| {
+            recording
+                .exchanges()
+                .iter()
+                .find(|e| e.request().code() == code.code())
+                .unwrap()
+                .response()
+        };
+        let max_offset = response(RequestCode::GetmaxOffset);
+        assert_eq!(max_offset.version(), Some(397));
+        assert_eq!(max_offset.flag(), 1);
+        assert_eq!(max_offset.language(), Some("RUST"));
+        assert_eq!(
+            response(RequestCode::GetMinOffset).remark(),
+            Some("topic not exist")
+        );
+        assert_eq!(recording.exchanges()[1].addr(), broker_addr);
+        assert_eq!(
+            recording.exchanges()[1].request().ext_fields()["topic"],
+            "order_topic"
+        );
+
+        // 回放的时候Broker的地址被替换为本地MockServer的地址
+        let replay = Replay::start(&recording).await.unwrap();
+        let replay_broker = replay.server(&broker_addr).unwrap().addr().to_string();
+        let mut client = Client::connection(replay.namesrv_addr()).await.unwrap();
+        assert_eq!(
+            client.broker_info().await.unwrap().all_broker_addrs(),
+            vec![&replay_broker]
+        );
+        let stats = client
+            .topic_stats(&replay_broker, "pay_topic".to_string())
+            .await
+            .unwrap();
+        assert_eq!(max_offsets(&stats), vec![7]);
+        let stats = client
+            .topic_stats(&replay_broker, "order_topic".to_string())
+            .await
+            .unwrap();
+        assert_eq!(max_offsets(&stats), vec![120]);
+        assert_eq!(client.max_offset(&mq).await, Some(42));
+        // extFields和录制不一致的请求使用最后录制的响应
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 3);
+        assert_eq!(client.max_offset(&mq).await, Some(42));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_record_error() {
+        let namesrv = MockServer::start().await.unwrap();
+        namesrv.on(
+            RequestCode::GetAllTopicListFromNameserver,
+            MockResponse::success(r#"{"topicList":[]}"#),
+        );
+        // 写/dev/full总是返回ENOSPC，录制失败的请求返回错误
+        let recorder = Recorder::create("/dev/full").unwrap();
+        let socket = TcpStream::connect(namesrv.addr()).await.unwrap();
+        let mut connection = Connection::new(socket).with_recorder("namesrv", Some(recorder));
+        let error = connection
+            .send_request(RemotingCommand::new(
+                RequestCode::GetAllTopicListFromNameserver,
+            ))
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("Record exchange with namesrv error"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_production_recording() {
+        // 生产环境录制的响应：broker-b没有请求，TopicStats的key是fastjson输出的对象
+        let recording = Recording::parse(
+            r#"
+{"addr":"10.20.141.70:9876","request":{"code":106},"response":{"code":0,"body":"{\"brokerAddrTable\":{\"broker-b\":{\"brokerAddrs\":{0:\"10.20.141.72:20911\",1:\"10.20.141.74:20911\"},\"brokerName\":\"broker-b\",\"cluster\":\"rocketmq-cpaas\"},\"broker-a\":{\"brokerAddrs\":{0:\"10.20.141.73:20911\"},\"brokerName\":\"broker-a\",\"cluster\":\"rocketmq-cpaas\"}},\"clusterAddrTable\":{\"rocketmq-cpaas\":[\"broker-b\",\"broker-a\"]}}"}}
+{"addr":"10.20.141.73:20911","request":{"code":202,"extFields":{"topic":"%RETRY%test_submit_68985_l4"}},"response":{"code":0,"body":"{\"offsetTable\":{{\"brokerName\":\"broker-a\",\"queueId\":0,\"topic\":\"%RETRY%test_submit_68985_l4\"}:{\"lastUpdateTimestamp\":0,\"maxOffset\":3,\"minOffset\":0}}}"}}
+{"addr":"10.20.141.73:20911","request":{"code":202,"extFields":{"topic":"missing_topic"}},"response":{"code":17,"flag":1,"language":"JAVA","version":397,"remark":"No topic route info in name server for the topic: missing_topic"}}
+{"addr":"10.20.141.73:20911","request":{"code":33,"extFields":{"offset":"51426"}},"response":{"code":0,"bodyHex":"00ff10"}}
+{"addr":"10.20.141.73:20911","request":{"code":28},"response":{"code":1,"remark":"broker busy","body":"{\"brokerAddr\":\"10.20.141.73:20911\"}"}}
+"#,
+        )
+        .unwrap();
+        assert_eq!(recording.namesrv_addr(), Some("10.20.141.70:9876"));
+        assert_eq!(
+            recording.exchanges()[3].response().body_bytes(),
+            Bytes::from_static(&[0x00, 0xff, 0x10])
+        );
+
+        let replay = Replay::start(&recording).await.unwrap();
+        assert!(replay.server("10.20.141.72:20911").is_some());
+        let mut client = Client::connection(replay.namesrv_addr()).await.unwrap();
+        let broker_a = replay
+            .server("10.20.141.73:20911")
+            .unwrap()
+            .addr()
+            .to_string();
+        let stats = client
+            .topic_stats(&broker_a, "%RETRY%test_submit_68985_l4".to_string())
+            .await
+            .unwrap();
+        assert_eq!(max_offsets(&stats), vec![3]);
+        assert!(client
+            .topic_stats(&broker_a, "missing_topic".to_string())
+            .await
+            .is_none());
+
+        // 回放的响应保留录制的remark和version
+        let socket = TcpStream::connect(&broker_a).await.unwrap();
+        let response = Connection::new(socket)
+            .send_request(RemotingCommand::build(
+                RequestCode::GetTOpicStatsInfo,
+                Some(GetTopicStatsInfoHeader::new("missing_topic".to_string())),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.code(), 17);
+        assert_eq!(response.header().version(), 397);
+        assert_eq!(
+            response.header().remark(),
+            Some("No topic route info in name server for the topic: missing_topic")
+        );
+
+        // 错误的响应也回放录制的body
+        let socket = TcpStream::connect(&broker_a).await.unwrap();
+        let response = Connection::new(socket)
+            .send_request(RemotingCommand::new(RequestCode::GetBrokerRuntimeInfo))
+            .await
+            .unwrap();
+        assert_eq!(response.code(), 1);
+        assert_eq!(response.body(), format!(r#"{{"brokerAddr":"{broker_a}"}}"#));
+    }
+
+    #[test]
+    fn test_addr_rewrite_boundaries() {
+        let rewrite = AddrRewrite::new(HashMap::from([
+            ("10.0.0.1:10911".to_string(), "127.0.0.1:1".to_string()),
+            ("10.0.0.1:1091".to_string(), "127.0.0.1:2".to_string()),
+        ]));
+        assert_eq!(
+            rewrite.apply(r#"["10.0.0.1:10911","110.0.0.1:10911","10.0.0.1:1091"]"#),
+            r#"["127.0.0.1:1","110.0.0.1:10911","127.0.0.1:2"]"#
+        );
+        assert_eq!(
+            rewrite.apply("10.0.0.1:10911;10.0.0.1:1091;10.0.0.1:109111"),
+            "127.0.0.1:1;127.0.0.1:2;10.0.0.1:109111"
+        );
+    }
+}
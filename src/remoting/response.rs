@@ -1,16 +1,17 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     vec,
 };
 
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
-use crate::util::json::{TokenType, Tokenizer};
+use crate::util::json::Tokenizer;
 
 ///
 /// RocketMQ的信息的Master的ID，是: 0
 const MASTER_KEY: i64 = 0;
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct BrokerInformation {
     broker_addr_table: HashMap<String, BrokerData>,
@@ -18,10 +19,9 @@ pub struct BrokerInformation {
 }
 
 impl BrokerInformation {
-    pub fn parse(source: String) -> BrokerInformation {
+    pub fn parse(source: String) -> Option<BrokerInformation> {
         let json = Tokenizer::new(source).regular_json();
-        let b: BrokerInformation = serde_json::from_str(&json).unwrap();
-        b
+        serde_json::from_str(&json).ok()
     }
 
     pub fn all_broker_addrs(&self) -> Vec<&String> {
@@ -36,23 +36,68 @@ impl BrokerInformation {
         }
         addrs
     }
+
+    ///
+    /// 获取所有的Broker，按照broker name排序
+    pub fn brokers(&self) -> Vec<&BrokerData> {
+        let mut brokers: Vec<&BrokerData> = self.broker_addr_table.values().collect();
+        brokers.sort_by(|a, b| a.broker_name.cmp(&b.broker_name));
+        brokers
+    }
+
+    ///
+    /// 获取所有存在Master节点的Broker，按照broker name排序
+    pub fn master_brokers(&self) -> Vec<&BrokerData> {
+        self.brokers()
+            .into_iter()
+            .filter(|b| b.master_broker_addrs().is_some())
+            .collect()
+    }
+
+    ///
+    /// 获取集群中所有Master节点的地址，按照broker name排序
+    pub fn cluster_master_addrs(&self, cluster: &str) -> Vec<&String> {
+        let broker_names = match self.cluster_addr_table.get(cluster) {
+            Some(broker_names) => broker_names,
+            None => return vec![],
+        };
+        self.master_brokers()
+            .into_iter()
+            .filter(|b| broker_names.contains(&b.broker_name))
+            .filter_map(|b| b.master_broker_addrs())
+            .collect()
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct BrokerData {
+pub struct BrokerData {
     cluster: String,
     broker_name: String,
     broker_addrs: HashMap<i64, String>,
 }
 
 impl BrokerData {
-    fn master_broker_addrs(&self) -> Option<&String> {
+    pub fn master_broker_addrs(&self) -> Option<&String> {
         self.broker_addrs.get(&MASTER_KEY)
     }
+
+    pub fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    pub fn broker_name(&self) -> &str {
+        &self.broker_name
+    }
+
+    ///
+    /// Broker所有节点的地址，key是broker id，0是Master
+    pub fn broker_addrs(&self) -> &HashMap<i64, String> {
+        &self.broker_addrs
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TopicRouteInformation {
     #[serde(default)]
@@ -64,14 +109,26 @@ pub struct TopicRouteInformation {
 }
 
 impl TopicRouteInformation {
-    pub fn parse(source: String) -> TopicRouteInformation {
+    pub fn parse(source: String) -> Option<TopicRouteInformation> {
         let json = Tokenizer::new(source).regular_json();
-        serde_json::from_str(&json).unwrap()
+        serde_json::from_str(&json).ok()
+    }
+
+    ///
+    /// Topic所在的Broker列表
+    pub fn brokers(&self) -> &Vec<BrokerData> {
+        &self.broker_datas
+    }
+
+    ///
+    /// Topic在每个Broker上的队列数量和权限
+    pub fn queues(&self) -> &Vec<QueueData> {
+        &self.queue_datas
     }
 }
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct QueueData {
+pub struct QueueData {
     broker_name: String,
     read_queue_nums: i32,
     write_queue_nums: i32,
@@ -79,6 +136,24 @@ struct QueueData {
     topic_sys_flag: i32,
 }
 
+impl QueueData {
+    pub fn broker_name(&self) -> &str {
+        &self.broker_name
+    }
+
+    pub fn read_queue_nums(&self) -> i32 {
+        self.read_queue_nums
+    }
+
+    pub fn write_queue_nums(&self) -> i32 {
+        self.write_queue_nums
+    }
+
+    pub fn perm(&self) -> i32 {
+        self.perm
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Topics {
     #[serde(rename = "topicList")]
@@ -86,8 +161,8 @@ pub struct Topics {
 }
 
 impl Topics {
-    pub fn parse(source: String) -> Topics {
-        serde_json::from_str(&source).unwrap()
+    pub fn parse(source: String) -> Option<Topics> {
+        serde_json::from_str(&source).ok()
     }
 
     pub fn topics(&self) -> &Vec<String> {
@@ -101,162 +176,198 @@ pub struct TopicStats {
 }
 
 impl TopicStats {
-    pub fn parse(source: String) -> TopicStats {
-        TopicStats {
-            offset_table: Self::do_parse(source),
-        }
+    pub fn parse(source: String) -> Option<TopicStats> {
+        Some(TopicStats {
+            offset_table: Self::do_parse(source)?,
+        })
     }
 
-    fn do_parse(source: String) -> HashMap<MessageQueue, TopicOffset> {
-        let mut tokenizer = Tokenizer::new(source);
-        tokenizer.parse();
-        let tokens = tokenizer.tokens();
-        let offset_table = &tokens[4..tokens.len() - 3];
-        let mut iter = offset_table.iter();
-        let mut obj_vec = vec![];
-        loop {
-            match iter.next() {
-                Some(token_type) => match token_type {
-                    TokenType::BeginObject(c) => {
-                        let mut obj_token_type = vec![];
-                        obj_token_type.push(token_type.clone());
-                        loop {
-                            match iter.next() {
-                                Some(obj_type) => match obj_type {
-                                    TokenType::EndObject(oc) => {
-                                        obj_token_type.push(obj_type.clone());
-                                        obj_vec.push(obj_token_type);
-                                        break;
-                                    }
-                                    _ => {
-                                        obj_token_type.push(obj_type.clone());
-                                    }
-                                },
-                                None => {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                },
-                None => {
-                    break;
-                }
-            }
+    pub fn offset_table(&self) -> &HashMap<MessageQueue, TopicOffset> {
+        &self.offset_table
+    }
+
+    ///
+    /// 合并Topic在另一个Broker上的统计信息
+    pub fn merge(&mut self, other: TopicStats) {
+        self.offset_table.extend(other.offset_table);
+    }
+
+    ///
+    /// offsetTable的key是MessageQueue对象，正规化以后是MessageQueue的JSON字符串
+    fn do_parse(source: String) -> Option<HashMap<MessageQueue, TopicOffset>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RawTopicStats {
+            offset_table: HashMap<String, TopicOffset>,
         }
 
-        let mut iter = obj_vec.iter().enumerate();
-        let mut data_map = HashMap::new();
-        loop {
-            match iter.next() {
-                Some((_, v)) => {
-                    let json = Tokenizer::do_regular_json(v);
-                    let message_queue: MessageQueue = serde_json::from_str(json.as_str()).unwrap();
-                    let (_, t) = iter.next().unwrap();
-                    let topic_offset: TopicOffset =
-                        serde_json::from_str(Tokenizer::do_regular_json(t).as_str()).unwrap();
-                    data_map.insert(message_queue, topic_offset);
-                }
-                None => {
-                    break;
-                }
-            }
+        let json = Tokenizer::new(source).regular_json();
+        let raw: RawTopicStats = serde_json::from_str(&json).ok()?;
+        raw.offset_table
+            .into_iter()
+            .map(|(key, offset)| Some((serde_json::from_str(&key).ok()?, offset)))
+            .collect()
+    }
+}
+
+///
+/// offsetTable的key是对象，不能作为JSON的key，序列化为按照Topic、Broker、队列排序的列表:
+/// [{"topic":"order_topic","brokerName":"broker-a","queueId":0,"minOffset":0,"maxOffset":10,"lastUpdateTimestamp":0}]
+impl Serialize for TopicStats {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct QueueOffset<'a> {
+            #[serde(flatten)]
+            message_queue: &'a MessageQueue,
+            #[serde(flatten)]
+            offset: &'a TopicOffset,
         }
-        data_map
+
+        let mut queues: Vec<_> = self.offset_table.iter().collect();
+        queues.sort_by(|(a, _), (b, _)| {
+            (&a.topic, &a.broker_name, a.queue_id).cmp(&(&b.topic, &b.broker_name, b.queue_id))
+        });
+        serializer.collect_seq(
+            queues
+                .into_iter()
+                .map(|(message_queue, offset)| QueueOffset {
+                    message_queue,
+                    offset,
+                }),
+        )
     }
 }
 
-#[derive(Debug, Deserialize, Hash)]
+#[derive(Debug, Deserialize, Serialize, Hash, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase")]
-struct MessageQueue {
+pub struct MessageQueue {
     broker_name: String,
     queue_id: i32,
     topic: String,
 }
 
-impl Eq for MessageQueue {}
+impl MessageQueue {
+    pub fn new(topic: String, broker_name: String, queue_id: i32) -> MessageQueue {
+        MessageQueue {
+            broker_name,
+            queue_id,
+            topic,
+        }
+    }
 
-impl PartialEq for MessageQueue {
-    fn eq(&self, other: &Self) -> bool {
-        self.broker_name.eq(&other.broker_name)
-            && self.queue_id.eq(&other.queue_id)
-            && self.topic.eq(&other.topic)
+    pub fn broker_name(&self) -> &str {
+        &self.broker_name
     }
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+
+    pub fn queue_id(&self) -> i32 {
+        self.queue_id
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
     }
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct TopicOffset {
+pub struct TopicOffset {
     min_offset: i64,
     max_offset: i64,
     last_update_timestamp: i64,
 }
 
+impl TopicOffset {
+    pub fn min_offset(&self) -> i64 {
+        self.min_offset
+    }
+
+    pub fn max_offset(&self) -> i64 {
+        self.max_offset
+    }
+
+    ///
+    /// 最后一条消息的存储时间，单位是毫秒，队列为空的时候是0
+    pub fn last_update_timestamp(&self) -> i64 {
+        self.last_update_timestamp
+    }
+}
+
 ///
 /// Topic的消费者组的列表对象
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConsumerGroups {
     group_list: Vec<String>,
 }
 
 impl ConsumerGroups {
-    pub fn parse(source: String) -> ConsumerGroups {
-        serde_json::from_str(&source).unwrap()
+    pub fn parse(source: String) -> Option<ConsumerGroups> {
+        serde_json::from_str(&source).ok()
+    }
+
+    pub fn groups(&self) -> &Vec<String> {
+        &self.group_list
     }
 }
 
-#[derive(Debug, Deserialize)]
+///
+/// 序列化的时候和Broker返回的格式一致: {"table":{...}}，key按照名字排序
+#[derive(Debug, Serialize)]
 pub struct BrokerRuntimeInfo {
+    #[serde(skip)]
     table: BrokerRuntimeInfoTable,
+    #[serde(rename = "table")]
+    entries: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawBrokerRuntimeInfo {
+    table: BTreeMap<String, String>,
 }
 
 impl BrokerRuntimeInfo {
-    pub fn from(source: String) -> BrokerRuntimeInfo {
-        serde_json::from_str(&source).unwrap()
+    pub fn from(source: String) -> Option<BrokerRuntimeInfo> {
+        let raw: RawBrokerRuntimeInfo = serde_json::from_str(&source).ok()?;
+        let table = serde_json::from_value(serde_json::to_value(&raw.table).ok()?).ok()?;
+        Some(BrokerRuntimeInfo {
+            table,
+            entries: raw.table,
+        })
+    }
+
+    pub fn table(&self) -> &BrokerRuntimeInfoTable {
+        &self.table
+    }
+
+    ///
+    /// Broker返回的所有运行时信息，按照名字排序
+    pub fn entries(&self) -> &BTreeMap<String, String> {
+        &self.entries
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+///
+/// 延迟消息的Topic: SCHEDULE_TOPIC_XXXX每个延迟级别的投递进度，broker的格式是: 已投递offset,最大offset
+const SCHEDULE_MESSAGE_OFFSET_PREFIX: &str = "scheduleMessageOffset_";
+///
+/// 多个commitlog存储路径的时候，每个路径的磁盘使用率: commitLogDiskRatio_/path/to/commitlog
+const COMMIT_LOG_DISK_RATIO: &str = "commitLogDiskRatio";
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct BrokerRuntimeInfoTable {
     #[serde(rename = "msgPutTotalTodayNow")]
     msg_put_total_today_now: String,
 
-    #[serde(rename = "scheduleMessageOffset_2")]
-    schedule_message_offset_2: String,
-
-    #[serde(rename = "scheduleMessageOffset_3")]
-    schedule_message_offset_3: String,
-
     #[serde(rename = "sendThreadPoolQueueHeadWaitTimeMills")]
     send_thread_pool_queue_head_wait_time_mills: String,
 
     #[serde(rename = "putMessageDistributeTime")]
     put_message_distribute_time: String,
 
-    #[serde(rename = "scheduleMessageOffset_9")]
-    schedule_message_offset_9: String,
-
-    #[serde(rename = "scheduleMessageOffset_4")]
-    schedule_message_offset_4: String,
-
-    #[serde(rename = "scheduleMessageOffset_5")]
-    schedule_message_offset_5: String,
-
     #[serde(rename = "queryThreadPoolQueueHeadWaitTimeMills")]
     query_thread_pool_queue_head_wait_time_mills: String,
 
-    #[serde(rename = "scheduleMessageOffset_7")]
-    schedule_message_offset_7: String,
-
-    #[serde(rename = "scheduleMessageOffset_6")]
-    schedule_message_offset_6: String,
-
     #[serde(rename = "remainHowManyDataToFlush")]
     remain_how_many_data_to_flush: String,
 
@@ -307,30 +418,12 @@ pub struct BrokerRuntimeInfoTable {
     #[serde(rename = "getTotalTps")]
     get_total_tps: String,
 
-    #[serde(rename = "scheduleMessageOffset_11")]
-    schedule_message_offset_11: String,
-
-    #[serde(rename = "scheduleMessageOffset_12")]
-    schedule_message_offset_12: String,
-
-    #[serde(rename = "scheduleMessageOffset_13")]
-    schedule_message_offset_13: String,
-
     #[serde(rename = "consumeQueueDiskRatio")]
     consume_queue_disk_ratio: String,
 
     #[serde(rename = "getFoundTps")]
     get_found_tps: String,
 
-    #[serde(rename = "scheduleMessageOffset_16")]
-    schedule_message_offset_16: String,
-
-    #[serde(rename = "scheduleMessageOffset_17")]
-    schedule_message_offset_17: String,
-
-    #[serde(rename = "scheduleMessageOffset_18")]
-    schedule_message_offset_18: String,
-
     #[serde(rename = "EndTransactionThreadPoolQueueCapacity")]
     end_transaction_thread_pool_queue_capacity: String,
 
@@ -387,35 +480,788 @@ pub struct BrokerRuntimeInfoTable {
 
     #[serde(rename = "pullThreadPoolQueueCapacity")]
     pull_thread_pool_queue_capacity: String,
+
+    ///
+    /// 没有固定名字的字段，例如: scheduleMessageOffset_N
+    #[serde(flatten)]
+    others: HashMap<String, String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl BrokerRuntimeInfoTable {
+    ///
+    /// 解析所有延迟级别的投递进度，按照延迟级别排序
+    pub fn schedule_message_offsets(&self) -> Vec<ScheduleMessageOffset> {
+        let mut offsets: Vec<ScheduleMessageOffset> = self
+            .others
+            .iter()
+            .filter_map(|(key, value)| {
+                let level = key
+                    .strip_prefix(SCHEDULE_MESSAGE_OFFSET_PREFIX)?
+                    .parse()
+                    .ok()?;
+                ScheduleMessageOffset::parse(level, value)
+            })
+            .collect();
+        offsets.sort_by_key(|o| o.delay_level);
+        offsets
+    }
 
-    #[test]
-    fn test_serialize_complex_hashmap() {
-        let json = r#"
-          {"offsetTable":
-          {
-            {
-                "brokerName":"broker-a",
-                "queueId":0,
-                "topic":"%RETRY%test_submit_68985_l4"
-            }:{
-                "lastUpdateTimestamp":0,
-                "maxOffset":0,
-                "minOffset":0}
-          }
-          }
-        "#;
+    ///
+    /// 解析putMessageDistributeTime，格式: [<=0ms]:0 [0~10ms]:0 ... [10s~]:0，每个区间是独立计数
+    pub fn put_message_distribute_time(&self) -> Vec<LatencyBucket> {
+        self.put_message_distribute_time
+            .split_whitespace()
+            .filter_map(LatencyBucket::parse)
+            .collect()
+    }
 
-        let topic_stats = TopicStats::parse(json.to_string());
+    ///
+    /// 解析commitLogDirCapacity，格式: Total : 299.3 GiB, Free : 276.1 GiB.
+    /// 多个存储路径的时候每个路径一项，用`; `分隔，例如: /data1/commitlog: Total : 299.3 GiB, Free : 276.1 GiB; ...，结果是所有路径的合计。
+    /// 数字按照Broker的locale格式化，小数点可能是`,`，例如: Total : 299,3 GiB, Free : 276,1 GiB.
+    pub fn commit_log_dir_capacity(&self) -> Option<DirCapacity> {
+        let mut capacity: Option<DirCapacity> = None;
+        for entry in self
+            .commit_log_dir_capacity
+            .split(';')
+            .map(|entry| entry.trim().trim_end_matches('.'))
+            .filter(|entry| !entry.is_empty())
+        {
+            let total = entry.find("Total")?;
+            let free = entry.find("Free")?;
+            let total_value = entry.get(total + "Total".len()..free)?;
+            let free_value = entry.get(free + "Free".len()..)?;
+            let total_bytes = parse_bytes(
+                total_value
+                    .trim()
+                    .trim_start_matches(':')
+                    .trim_end_matches([',', ' ']),
+            )?;
+            let free_bytes = parse_bytes(free_value.trim().trim_start_matches(':'))?;
+            let sum = capacity.get_or_insert(DirCapacity {
+                total_bytes: 0,
+                free_bytes: 0,
+            });
+            sum.total_bytes += total_bytes;
+            sum.free_bytes += free_bytes;
+        }
+        capacity
     }
 
-    #[test]
-    fn test_deserialize_broker_runtime_stats_information() {
-        let json = r#"
+    ///
+    /// commitlog所在磁盘的使用率
+    pub fn commit_log_disk_ratio(&self) -> Option<f64> {
+        self.others.get(COMMIT_LOG_DISK_RATIO)?.trim().parse().ok()
+    }
+
+    ///
+    /// consume queue所在磁盘的使用率
+    pub fn consume_queue_disk_ratio(&self) -> Option<f64> {
+        self.consume_queue_disk_ratio.trim().parse().ok()
+    }
+
+    ///
+    /// 每个commitlog存储路径的磁盘使用率，按照路径排序
+    pub fn commit_log_path_disk_ratios(&self) -> Vec<(String, f64)> {
+        let prefix = format!("{}_", COMMIT_LOG_DISK_RATIO);
+        let mut ratios: Vec<(String, f64)> = self
+            .others
+            .iter()
+            .filter_map(|(key, value)| {
+                let path = key.strip_prefix(&prefix)?;
+                Some((path.to_string(), value.trim().parse().ok()?))
+            })
+            .collect();
+        ratios.sort_by(|a, b| a.0.cmp(&b.0));
+        ratios
+    }
+
+    ///
+    /// 还没有刷盘的数据量，单位是字节
+    pub fn remain_how_many_data_to_flush(&self) -> Option<u64> {
+        parse_bytes(&self.remain_how_many_data_to_flush)
+    }
+
+    pub fn put_tps(&self) -> Option<WindowTps> {
+        WindowTps::parse(&self.put_tps)
+    }
+
+    pub fn get_total_tps(&self) -> Option<WindowTps> {
+        WindowTps::parse(&self.get_total_tps)
+    }
+
+    pub fn get_found_tps(&self) -> Option<WindowTps> {
+        WindowTps::parse(&self.get_found_tps)
+    }
+
+    pub fn get_miss_tps(&self) -> Option<WindowTps> {
+        WindowTps::parse(&self.get_miss_tps)
+    }
+
+    pub fn get_transfered_tps(&self) -> Option<WindowTps> {
+        WindowTps::parse(&self.get_transfered_tps)
+    }
+}
+
+///
+/// Broker统计的TPS，格式: 98.79 123.28 121.89，分别是最近10秒、1分钟、10分钟的TPS
+#[derive(Debug, PartialEq)]
+pub struct WindowTps {
+    ten_seconds: f64,
+    one_minute: f64,
+    ten_minutes: f64,
+}
+
+impl WindowTps {
+    pub fn parse(source: &str) -> Option<WindowTps> {
+        let mut values = source.split_whitespace().map(|v| v.parse::<f64>());
+        let tps = WindowTps {
+            ten_seconds: values.next()?.ok()?,
+            one_minute: values.next()?.ok()?,
+            ten_minutes: values.next()?.ok()?,
+        };
+        Some(tps)
+    }
+
+    ///
+    /// 每个统计窗口的TPS: (窗口, TPS)
+    pub fn windows(&self) -> [(&'static str, f64); 3] {
+        [
+            ("10s", self.ten_seconds),
+            ("1m", self.one_minute),
+            ("10m", self.ten_minutes),
+        ]
+    }
+}
+
+///
+/// 存储目录的容量，单位是字节
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirCapacity {
+    total_bytes: u64,
+    free_bytes: u64,
+}
+
+impl DirCapacity {
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.free_bytes
+    }
+}
+
+///
+/// 解析Broker输出的可读的数据大小: 0 B、299.3 GiB，兼容1000进制的单位: 1.2 MB、3 kB
+fn parse_bytes(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.split_once(' ') {
+        Some((number, unit)) => (number, unit.trim()),
+        None => {
+            let i = value.find(|c: char| c.is_ascii_alphabetic())?;
+            (&value[..i], &value[i..])
+        }
+    };
+    // humanReadableByteCount按照Broker的locale格式化，小数点可能是`,`
+    let number: f64 = number.trim().replace(',', ".").parse().ok()?;
+    let multiplier: f64 = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024f64.powi(2),
+        "GiB" => 1024f64.powi(3),
+        "TiB" => 1024f64.powi(4),
+        "PiB" => 1024f64.powi(5),
+        "EiB" => 1024f64.powi(6),
+        "kB" | "KB" => 1000.0,
+        "MB" => 1000f64.powi(2),
+        "GB" => 1000f64.powi(3),
+        "TB" => 1000f64.powi(4),
+        "PB" => 1000f64.powi(5),
+        "EB" => 1000f64.powi(6),
+        _ => return None,
+    };
+    Some((number * multiplier).round() as u64)
+}
+
+///
+/// 耗时分布的一个区间，单位是秒
+#[derive(Debug, PartialEq)]
+pub struct LatencyBucket {
+    lower_bound: f64,
+    upper_bound: f64,
+    count: u64,
+}
+
+impl LatencyBucket {
+    pub fn parse(source: &str) -> Option<LatencyBucket> {
+        let (range, count) = source.strip_prefix('[')?.split_once("]:")?;
+        let (lower_bound, upper_bound) = if let Some(upper) = range.strip_prefix("<=") {
+            let upper = parse_seconds(upper, None)?;
+            (upper, upper)
+        } else {
+            let (lower, upper) = range.split_once('~')?;
+            if upper.is_empty() {
+                (parse_seconds(lower, None)?, f64::INFINITY)
+            } else {
+                // 下界没有单位的时候和上界一致，例如: 0~10ms、1~2s
+                let unit = if upper.ends_with("ms") { "ms" } else { "s" };
+                (
+                    parse_seconds(lower, Some(unit))?,
+                    parse_seconds(upper, None)?,
+                )
+            }
+        };
+        Some(LatencyBucket {
+            lower_bound,
+            upper_bound,
+            count: count.parse().ok()?,
+        })
+    }
+
+    pub fn lower_bound(&self) -> f64 {
+        self.lower_bound
+    }
+
+    pub fn upper_bound(&self) -> f64 {
+        self.upper_bound
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+///
+/// 解析带单位的耗时: 10ms、2s，转换为秒
+fn parse_seconds(value: &str, default_unit: Option<&str>) -> Option<f64> {
+    let (number, unit) = if let Some(number) = value.strip_suffix("ms") {
+        (number, "ms")
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, "s")
+    } else {
+        (value, default_unit?)
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if unit == "ms" {
+        Some(number / 1000.0)
+    } else {
+        Some(number)
+    }
+}
+
+///
+/// 延迟级别的投递进度
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScheduleMessageOffset {
+    delay_level: i32,
+    delivered_offset: i64,
+    max_offset: i64,
+}
+
+impl ScheduleMessageOffset {
+    fn parse(delay_level: i32, value: &str) -> Option<ScheduleMessageOffset> {
+        let (delivered, max) = value.split_once(',')?;
+        Some(ScheduleMessageOffset {
+            delay_level,
+            delivered_offset: delivered.trim().parse().ok()?,
+            max_offset: max.trim().parse().ok()?,
+        })
+    }
+
+    pub fn delay_level(&self) -> i32 {
+        self.delay_level
+    }
+
+    pub fn delivered_offset(&self) -> i64 {
+        self.delivered_offset
+    }
+
+    pub fn max_offset(&self) -> i64 {
+        self.max_offset
+    }
+
+    ///
+    /// 还没有投递的延迟消息数量
+    pub fn backlog(&self) -> i64 {
+        (self.max_offset - self.delivered_offset).max(0)
+    }
+}
+
+///
+/// Broker持久化的延迟消息投递进度(delayOffset.json)，key是延迟级别
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelayOffsets {
+    offset_table: HashMap<i32, i64>,
+}
+
+impl DelayOffsets {
+    pub fn parse(source: String) -> Option<DelayOffsets> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn offset(&self, delay_level: i32) -> Option<i64> {
+        self.offset_table.get(&delay_level).copied()
+    }
+}
+
+///
+/// Broker和Nameserver的配置信息，body是Java的Properties格式: key=value
+#[derive(Debug, Default)]
+pub struct ConfigProperties {
+    properties: HashMap<String, String>,
+}
+
+impl ConfigProperties {
+    pub fn parse(source: &str) -> ConfigProperties {
+        let mut properties = HashMap::new();
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                properties.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        ConfigProperties { properties }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.properties.get(key)
+    }
+
+    pub fn properties(&self) -> &HashMap<String, String> {
+        &self.properties
+    }
+}
+
+///
+/// 消费者组在一个Topic上每个队列的消费时间跨度
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeTimeSpans {
+    #[serde(default)]
+    consume_time_span_set: Vec<QueueTimeSpan>,
+}
+
+impl ConsumeTimeSpans {
+    pub fn parse(source: String) -> Option<ConsumeTimeSpans> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn time_spans(&self) -> &Vec<QueueTimeSpan> {
+        &self.consume_time_span_set
+    }
+}
+
+///
+/// 队列的消费时间跨度，时间单位是毫秒。
+/// delay_time是当前时间和最早的未消费消息的存储时间的差值，没有积压的时候是0
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueTimeSpan {
+    message_queue: MessageQueue,
+    #[serde(default)]
+    consume_time_stamp: i64,
+    #[serde(default)]
+    delay_time: i64,
+}
+
+impl QueueTimeSpan {
+    pub fn message_queue(&self) -> &MessageQueue {
+        &self.message_queue
+    }
+
+    pub fn consume_time_stamp(&self) -> i64 {
+        self.consume_time_stamp
+    }
+
+    pub fn delay_time(&self) -> i64 {
+        self.delay_time
+    }
+}
+
+///
+/// 消费者组在Topic上每个队列的消费进度
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeStats {
+    #[serde(default, deserialize_with = "deserialize_message_queue_map")]
+    offset_table: HashMap<MessageQueue, ConsumeOffset>,
+}
+
+impl ConsumeStats {
+    pub fn parse(source: String) -> Option<ConsumeStats> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    ///
+    /// 所有队列上还没有消费的消息数量
+    pub fn backlog(&self) -> i64 {
+        self.offset_table.values().map(|o| o.backlog()).sum()
+    }
+
+    pub fn offset_table(&self) -> &HashMap<MessageQueue, ConsumeOffset> {
+        &self.offset_table
+    }
+}
+
+///
+/// 队列在Broker上的最大offset以及消费者组提交的offset
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeOffset {
+    broker_offset: i64,
+    consumer_offset: i64,
+    #[serde(default)]
+    last_timestamp: i64,
+}
+
+impl ConsumeOffset {
+    pub fn broker_offset(&self) -> i64 {
+        self.broker_offset
+    }
+
+    pub fn consumer_offset(&self) -> i64 {
+        self.consumer_offset
+    }
+
+    ///
+    /// 最后一条已经消费的消息的存储时间，单位是毫秒
+    pub fn last_timestamp(&self) -> i64 {
+        self.last_timestamp
+    }
+
+    pub fn backlog(&self) -> i64 {
+        (self.broker_offset - self.consumer_offset).max(0)
+    }
+}
+
+///
+/// 重置消费位点以后每个队列的新offset
+#[cfg(feature = "admin")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetOffsetBody {
+    #[serde(default, deserialize_with = "deserialize_message_queue_map")]
+    offset_table: HashMap<MessageQueue, i64>,
+}
+
+#[cfg(feature = "admin")]
+impl ResetOffsetBody {
+    pub fn parse(source: String) -> Option<ResetOffsetBody> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn offset_table(&self) -> &HashMap<MessageQueue, i64> {
+        &self.offset_table
+    }
+}
+
+///
+/// 反序列化key是MessageQueue的Map，key已经被Tokenizer转换为JSON字符串
+fn deserialize_message_queue_map<'de, D, V>(
+    deserializer: D,
+) -> Result<HashMap<MessageQueue, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let map: HashMap<String, V> = HashMap::deserialize(deserializer)?;
+    map.into_iter()
+        .map(|(key, value)| {
+            let message_queue = serde_json::from_str(&key).map_err(serde::de::Error::custom)?;
+            Ok((message_queue, value))
+        })
+        .collect()
+}
+
+///
+/// 消费者组的在线客户端连接
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumerConnection {
+    #[serde(default)]
+    connection_set: Vec<ClientConnection>,
+    #[serde(default)]
+    subscription_table: HashMap<String, SubscriptionData>,
+    #[serde(default)]
+    consume_type: String,
+    #[serde(default)]
+    message_model: String,
+    #[serde(default)]
+    consume_from_where: String,
+}
+
+impl ConsumerConnection {
+    pub fn parse(source: String) -> Option<ConsumerConnection> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn connections(&self) -> &Vec<ClientConnection> {
+        &self.connection_set
+    }
+
+    pub fn consume_type(&self) -> &str {
+        &self.consume_type
+    }
+
+    pub fn message_model(&self) -> &str {
+        &self.message_model
+    }
+
+    ///
+    /// 消费者组的订阅关系，key是Topic
+    pub fn subscriptions(&self) -> &HashMap<String, SubscriptionData> {
+        &self.subscription_table
+    }
+
+    pub fn consume_from_where(&self) -> &str {
+        &self.consume_from_where
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientConnection {
+    client_id: String,
+    #[serde(default)]
+    client_addr: String,
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    version: i32,
+}
+
+impl ClientConnection {
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub fn client_addr(&self) -> &str {
+        &self.client_addr
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    ///
+    /// 客户端的版本号，是MQVersion.Version的序号
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+///
+/// 订阅关系
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionData {
+    topic: String,
+    #[serde(default)]
+    sub_string: String,
+    #[serde(default)]
+    expression_type: String,
+    #[serde(default)]
+    tags_set: Vec<String>,
+    #[serde(default)]
+    sub_version: i64,
+}
+
+impl SubscriptionData {
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    pub fn sub_string(&self) -> &str {
+        &self.sub_string
+    }
+
+    ///
+    /// 过滤表达式的类型: TAG或者SQL92
+    pub fn expression_type(&self) -> &str {
+        &self.expression_type
+    }
+
+    pub fn tags_set(&self) -> &Vec<String> {
+        &self.tags_set
+    }
+
+    pub fn sub_version(&self) -> i64 {
+        self.sub_version
+    }
+}
+
+///
+/// Broker从消费者客户端获取的运行时信息
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumerRunningInfo {
+    #[serde(default)]
+    properties: HashMap<String, Value>,
+    #[serde(default)]
+    subscription_set: Vec<SubscriptionData>,
+    #[serde(default, deserialize_with = "deserialize_message_queue_map")]
+    mq_table: HashMap<MessageQueue, ProcessQueueInfo>,
+    #[serde(default)]
+    status_table: HashMap<String, ConsumeStatus>,
+    #[serde(default)]
+    jstack: Option<String>,
+}
+
+///
+/// 消费者客户端的属性
+pub const PROP_CONSUMEORDERLY: &str = "PROP_CONSUMEORDERLY";
+pub const PROP_CLIENT_VERSION: &str = "PROP_CLIENT_VERSION";
+
+impl ConsumerRunningInfo {
+    ///
+    /// 运行时信息是消费者客户端自己上报的，不同语言和版本的客户端结构不一样，解析失败的时候返回None
+    pub fn parse(source: String) -> Option<ConsumerRunningInfo> {
+        let json = Tokenizer::new(source).regular_json();
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn property(&self, key: &str) -> Option<String> {
+        match self.properties.get(key)? {
+            Value::String(value) => Some(value.clone()),
+            value => Some(value.to_string()),
+        }
+    }
+
+    pub fn subscriptions(&self) -> &Vec<SubscriptionData> {
+        &self.subscription_set
+    }
+
+    pub fn mq_table(&self) -> &HashMap<MessageQueue, ProcessQueueInfo> {
+        &self.mq_table
+    }
+
+    pub fn status_table(&self) -> &HashMap<String, ConsumeStatus> {
+        &self.status_table
+    }
+
+    pub fn jstack(&self) -> Option<&String> {
+        self.jstack.as_ref()
+    }
+
+    ///
+    /// 统计jstack中每种线程状态的线程数，jstack每个线程的第一行格式: 线程名  TID: 1 STATE: RUNNABLE
+    pub fn thread_states(&self) -> BTreeMap<String, u64> {
+        let mut states = BTreeMap::new();
+        if let Some(jstack) = self.jstack.as_ref() {
+            for line in jstack.lines() {
+                if let Some((_, state)) = line.split_once("STATE: ") {
+                    *states.entry(state.trim().to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        states
+    }
+}
+
+///
+/// 消费者客户端中每个队列的处理情况
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProcessQueueInfo {
+    commit_offset: i64,
+    cached_msg_min_offset: i64,
+    cached_msg_max_offset: i64,
+    cached_msg_count: i64,
+    #[serde(rename = "cachedMsgSizeInMiB")]
+    cached_msg_size_in_mib: i64,
+    transaction_msg_min_offset: i64,
+    transaction_msg_max_offset: i64,
+    transaction_msg_count: i64,
+    locked: bool,
+    try_unlock_times: i64,
+    last_lock_timestamp: i64,
+    droped: bool,
+    last_pull_timestamp: i64,
+    last_consume_timestamp: i64,
+}
+
+impl ProcessQueueInfo {
+    pub fn cached_msg_count(&self) -> i64 {
+        self.cached_msg_count
+    }
+
+    pub fn cached_msg_size_in_mib(&self) -> i64 {
+        self.cached_msg_size_in_mib
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn last_pull_timestamp(&self) -> i64 {
+        self.last_pull_timestamp
+    }
+
+    pub fn last_consume_timestamp(&self) -> i64 {
+        self.last_consume_timestamp
+    }
+}
+
+///
+/// 消费者客户端每个Topic的拉取和消费统计，RT的单位是毫秒
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct ConsumeStatus {
+    #[serde(rename = "pullRT")]
+    pull_rt: f64,
+    #[serde(rename = "pullTPS")]
+    pull_tps: f64,
+    #[serde(rename = "consumeRT")]
+    consume_rt: f64,
+    #[serde(rename = "consumeOKTPS")]
+    consume_ok_tps: f64,
+    #[serde(rename = "consumeFailedTPS")]
+    consume_failed_tps: f64,
+    #[serde(rename = "consumeFailedMsgs")]
+    consume_failed_msgs: i64,
+}
+
+impl ConsumeStatus {
+    pub fn pull_rt(&self) -> f64 {
+        self.pull_rt
+    }
+
+    pub fn pull_tps(&self) -> f64 {
+        self.pull_tps
+    }
+
+    pub fn consume_rt(&self) -> f64 {
+        self.consume_rt
+    }
+
+    pub fn consume_ok_tps(&self) -> f64 {
+        self.consume_ok_tps
+    }
+
+    pub fn consume_failed_tps(&self) -> f64 {
+        self.consume_failed_tps
+    }
+
+    pub fn consume_failed_msgs(&self) -> i64 {
+        self.consume_failed_msgs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BROKER_RUNTIME_INFO: &str = r#"
         {
   "table": {
     "msgPutTotalTodayNow": "202317086",
@@ -484,6 +1330,271 @@ mod tests {
 }
         "#;
 
-        BrokerRuntimeInfo::from(json.to_string());
+    #[test]
+    fn test_serialize_complex_hashmap() {
+        let json = r#"
+          {"offsetTable":
+          {
+            {
+                "brokerName":"broker-a",
+                "queueId":0,
+                "topic":"%RETRY%test_submit_68985_l4"
+            }:{
+                "lastUpdateTimestamp":0,
+                "maxOffset":0,
+                "minOffset":0}
+          }
+          }
+        "#;
+
+        let stats = TopicStats::parse(json.to_string()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            serde_json::json!([{
+                "brokerName": "broker-a",
+                "queueId": 0,
+                "topic": "%RETRY%test_submit_68985_l4",
+                "minOffset": 0,
+                "maxOffset": 0,
+                "lastUpdateTimestamp": 0
+            }])
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_bodies() {
+        assert!(TopicStats::parse(String::new()).is_none());
+        assert!(TopicStats::parse(r#"{"offsetTable":"#.to_string()).is_none());
+        assert!(TopicStats::parse(
+            r#"{"offsetTable":{{"brokerName":"broker-a"}:{"maxOffset":1}}}"#.to_string()
+        )
+        .is_none());
+        assert!(TopicStats::parse(r#"{"offsetTable":{}}"#.to_string())
+            .unwrap()
+            .offset_table()
+            .is_empty());
+        assert!(BrokerInformation::parse("<html>".to_string()).is_none());
+        assert!(TopicRouteInformation::parse("{}".to_string()).is_none());
+        assert!(Topics::parse(String::new()).is_none());
+        assert!(ConsumerGroups::parse(r#"{"groupList":"#.to_string()).is_none());
+        assert!(BrokerRuntimeInfo::from(r#"{"table":[]}"#.to_string()).is_none());
+    }
+
+    #[test]
+    fn test_deserialize_broker_runtime_stats_information() {
+        let info = BrokerRuntimeInfo::from(BROKER_RUNTIME_INFO.to_string()).unwrap();
+        assert_eq!(info.entries()["brokerVersionDesc"], "V4_9_2");
+        assert_eq!(info.entries()["scheduleMessageOffset_2"], "330248,13302481");
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["table"]["brokerVersionDesc"], "V4_9_2");
+    }
+
+    #[test]
+    fn test_parse_schedule_message_offsets() {
+        let info = BrokerRuntimeInfo::from(BROKER_RUNTIME_INFO.to_string()).unwrap();
+        let offsets = info.table().schedule_message_offsets();
+        let levels: Vec<i32> = offsets.iter().map(|o| o.delay_level()).collect();
+        assert_eq!(levels, (2..=18).collect::<Vec<i32>>());
+        assert_eq!(offsets[0].delivered_offset(), 330248);
+        assert_eq!(offsets[0].max_offset(), 13302481);
+        assert_eq!(offsets[0].backlog(), 13302481 - 330248);
+        assert_eq!(offsets[16].backlog(), 0);
+    }
+
+    #[test]
+    fn test_parse_put_message_distribute_time() {
+        let info = BrokerRuntimeInfo::from(BROKER_RUNTIME_INFO.to_string()).unwrap();
+        let buckets = info.table().put_message_distribute_time();
+        let bounds: Vec<(f64, f64)> = buckets
+            .iter()
+            .map(|b| (b.lower_bound(), b.upper_bound()))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                (0.0, 0.0),
+                (0.0, 0.01),
+                (0.01, 0.05),
+                (0.05, 0.1),
+                (0.1, 0.2),
+                (0.2, 0.5),
+                (0.5, 1.0),
+                (1.0, 2.0),
+                (2.0, 3.0),
+                (3.0, 4.0),
+                (4.0, 5.0),
+                (5.0, 10.0),
+                (10.0, f64::INFINITY),
+            ]
+        );
+        assert_eq!(
+            LatencyBucket::parse("[0~10ms]:1024"),
+            Some(LatencyBucket {
+                lower_bound: 0.0,
+                upper_bound: 0.01,
+                count: 1024
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_disk_usage() {
+        let info = BrokerRuntimeInfo::from(BROKER_RUNTIME_INFO.to_string()).unwrap();
+        let table = info.table();
+        assert_eq!(
+            table.commit_log_dir_capacity(),
+            Some(DirCapacity {
+                total_bytes: (299.3 * 1024f64.powi(3)).round() as u64,
+                free_bytes: (276.1 * 1024f64.powi(3)).round() as u64,
+            })
+        );
+        assert_eq!(table.commit_log_disk_ratio(), Some(0.08));
+        assert_eq!(table.consume_queue_disk_ratio(), Some(0.08));
+        assert_eq!(
+            table.commit_log_path_disk_ratios(),
+            vec![(
+                String::from("/home/soft/rocketmq-4.9.2_0905/store/commitlog"),
+                0.08
+            )]
+        );
+        assert_eq!(table.remain_how_many_data_to_flush(), Some(0));
+        assert_eq!(parse_bytes("1.5 KiB"), Some(1536));
+        assert_eq!(parse_bytes("1,5 KiB"), Some(1536));
+        let capacity = |value: &str| {
+            let json = BROKER_RUNTIME_INFO.replace("Total : 299.3 GiB, Free : 276.1 GiB.", value);
+            BrokerRuntimeInfo::from(json)
+                .unwrap()
+                .table()
+                .commit_log_dir_capacity()
+        };
+        assert_eq!(
+            capacity("Total : 299,3 GiB, Free : 276,1 GiB."),
+            table.commit_log_dir_capacity()
+        );
+        assert_eq!(
+            capacity("/data1/commitlog: Total : 2,0 GiB, Free : 1,5 GiB; /data2/commitlog: Total : 1,0 GiB, Free : 0,5 GiB; "),
+            Some(DirCapacity {
+                total_bytes: 3 * 1024u64.pow(3),
+                free_bytes: 2 * 1024u64.pow(3),
+            })
+        );
+        assert_eq!(capacity("Total : 299,3 GiB"), None);
+        assert_eq!(parse_bytes("3 kB"), Some(3000));
+        assert_eq!(parse_bytes("12 XB"), None);
+    }
+
+    #[test]
+    fn test_parse_window_tps() {
+        let info = BrokerRuntimeInfo::from(BROKER_RUNTIME_INFO.to_string()).unwrap();
+        let table = info.table();
+        assert_eq!(
+            table.get_total_tps().unwrap().windows(),
+            [
+                ("10s", 98.79012098790122),
+                ("1m", 123.2876712328767),
+                ("10m", 121.89155048345326)
+            ]
+        );
+        assert_eq!(table.put_tps().unwrap().windows()[2], ("10m", 0.0));
+        assert!(table.get_transfered_tps().is_some());
+        assert_eq!(WindowTps::parse("1.0 2.0"), None);
+    }
+
+    #[test]
+    fn test_parse_delay_offsets() {
+        let offsets =
+            DelayOffsets::parse(String::from(r#"{"offsetTable":{1:1024,2:330248,18:107}}"#))
+                .unwrap();
+        assert_eq!(offsets.offset(2), Some(330248));
+        assert_eq!(offsets.offset(3), None);
+    }
+
+    #[test]
+    fn test_parse_config_properties() {
+        let source = "brokerRole=ASYNC_MASTER\nflushDiskType=ASYNC_FLUSH\n\n# comment\nfileReservedTime=72\nnamesrvAddr=10.20.141.72:9876;10.20.141.73:9876\nbrokerIP2=\n";
+        let config = ConfigProperties::parse(source);
+        assert_eq!(config.get("brokerRole").unwrap(), "ASYNC_MASTER");
+        assert_eq!(config.get("fileReservedTime").unwrap(), "72");
+        assert_eq!(
+            config.get("namesrvAddr").unwrap(),
+            "10.20.141.72:9876;10.20.141.73:9876"
+        );
+        assert_eq!(config.get("brokerIP2").unwrap(), "");
+        assert_eq!(config.properties().len(), 5);
+    }
+
+    #[test]
+    fn test_parse_consumer_running_info() {
+        let json = r#"{"jstack":"ConsumeMessageThread_1                  TID: 45 STATE: WAITING\nConsumeMessageThread_1                  sun.misc.Unsafe.park(Native Method)\n\nPullMessageService                      TID: 40 STATE: RUNNABLE\n","mqTable":{{"brokerName":"broker-a","queueId":0,"topic":"order_topic"}:{"cachedMsgCount":12,"cachedMsgMaxOffset":1036,"cachedMsgMinOffset":1024,"cachedMsgSizeInMiB":1,"commitOffset":1024,"droped":false,"lastConsumeTimestamp":1706162317012,"lastLockTimestamp":1706162316000,"lastPullTimestamp":1706162317000,"locked":true,"transactionMsgCount":0,"transactionMsgMaxOffset":0,"transactionMsgMinOffset":0,"tryUnlockTimes":0}},"properties":{"PROP_CONSUME_TYPE":"CONSUME_PASSIVELY","PROP_CONSUMEORDERLY":"true","PROP_CLIENT_VERSION":"V4_9_2","PROP_THREADPOOL_CORE_SIZE":"20"},"statusTable":{"order_topic":{"consumeFailedMsgs":3,"consumeFailedTPS":0.0,"consumeOKTPS":12.5,"consumeRT":3.2,"pullRT":1.5,"pullTPS":20.0}},"subscriptionSet":[{"classFilterMode":false,"codeSet":[],"expressionType":"TAG","subString":"*","subVersion":1706162316000,"tagsSet":[],"topic":"order_topic"}]}"#;
+        let info = ConsumerRunningInfo::parse(json.to_string()).unwrap();
+        assert_eq!(info.property(PROP_CONSUMEORDERLY).unwrap(), "true");
+        assert_eq!(info.subscriptions()[0].topic(), "order_topic");
+
+        let (mq, process_queue) = info.mq_table().iter().next().unwrap();
+        assert_eq!(mq.broker_name(), "broker-a");
+        assert_eq!(mq.queue_id(), 0);
+        assert_eq!(process_queue.cached_msg_count(), 12);
+        assert!(process_queue.locked());
+
+        let status = info.status_table().get("order_topic").unwrap();
+        assert_eq!(status.consume_ok_tps(), 12.5);
+        assert_eq!(status.consume_failed_msgs(), 3);
+
+        let states = info.thread_states();
+        assert_eq!(states.get("WAITING"), Some(&1));
+        assert_eq!(states.get("RUNNABLE"), Some(&1));
+
+        // 其他语言的客户端上报的结构不一致
+        assert!(
+            ConsumerRunningInfo::parse(r#"{"mqTable":[],"properties":"x"}"#.to_string()).is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_consumer_connection() {
+        let json = r#"{"connectionSet":[{"clientAddr":"10.20.141.80:53124","clientId":"10.20.141.80@12345","language":"JAVA","version":397}],"consumeFromWhere":"CONSUME_FROM_LAST_OFFSET","consumeType":"CONSUME_PASSIVELY","messageModel":"CLUSTERING","subscriptionTable":{"order_topic":{"classFilterMode":false,"codeSet":[],"expressionType":"TAG","subString":"*","subVersion":1706162316000,"tagsSet":[],"topic":"order_topic"}}}"#;
+        let connection = ConsumerConnection::parse(json.to_string()).unwrap();
+        assert_eq!(
+            connection.connections()[0].client_id(),
+            "10.20.141.80@12345"
+        );
+        assert_eq!(connection.message_model(), "CLUSTERING");
+    }
+
+    #[test]
+    fn test_parse_consume_time_spans() {
+        let json = r#"{"consumeTimeSpanSet":[{"consumeTimeStamp":1706162310000,"delayTime":360000,"maxTimeStamp":1706162317012,"messageQueue":{"brokerName":"broker-a","queueId":1,"topic":"order_topic"},"minTimeStamp":1706000000000}]}"#;
+        let spans = ConsumeTimeSpans::parse(json.to_string()).unwrap();
+        let span = &spans.time_spans()[0];
+        assert_eq!(span.message_queue().queue_id(), 1);
+        assert_eq!(span.delay_time(), 360000);
+        assert_eq!(span.consume_time_stamp(), 1706162310000);
+    }
+
+    #[test]
+    fn test_parse_consume_stats() {
+        let json = r#"{"consumeTps":1.5,"offsetTable":{{"brokerName":"broker-a","queueId":0,"topic":"%RETRY%order_group"}:{"brokerOffset":120,"consumerOffset":100,"lastTimestamp":1706162317012},{"brokerName":"broker-a","queueId":1,"topic":"%RETRY%order_group"}:{"brokerOffset":7,"consumerOffset":7,"lastTimestamp":0}}}"#;
+        let stats = ConsumeStats::parse(json.to_string()).unwrap();
+        assert_eq!(stats.backlog(), 20);
+    }
+
+    #[cfg(feature = "admin")]
+    #[test]
+    fn test_parse_reset_offset_body() {
+        let json = r#"{"offsetTable":{{"brokerName":"broker-a","queueId":0,"topic":"order_topic"}:100,{"brokerName":"broker-a","queueId":1,"topic":"order_topic"}:98}}"#;
+        let body = ResetOffsetBody::parse(json.to_string()).unwrap();
+        let mq = MessageQueue::new("order_topic".to_string(), "broker-a".to_string(), 1);
+        assert_eq!(body.offset_table().get(&mq), Some(&98));
+    }
+
+    #[test]
+    fn test_cluster_master_addrs() {
+        let json = r#"{"brokerAddrTable":{"broker-b":{"brokerAddrs":{0:"10.20.141.72:20911",1:"10.20.141.82:20911"},"brokerName":"broker-b","cluster":"c1"},"broker-a":{"brokerAddrs":{0:"10.20.141.73:20911"},"brokerName":"broker-a","cluster":"c1"},"broker-c":{"brokerAddrs":{0:"10.20.141.74:20911"},"brokerName":"broker-c","cluster":"c2"}},"clusterAddrTable":{"c1":["broker-b","broker-a"],"c2":["broker-c"]}}"#;
+        let info = BrokerInformation::parse(json.to_string()).unwrap();
+        assert_eq!(
+            info.cluster_master_addrs("c1"),
+            vec!["10.20.141.73:20911", "10.20.141.72:20911"]
+        );
+        assert!(info.cluster_master_addrs("unknown").is_empty());
     }
 }
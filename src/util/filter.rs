@@ -0,0 +1,103 @@
+use regex::Regex;
+
+///
+/// 按照名字过滤Topic或者消费者组，正则表达式需要匹配整个名字。
+/// include为空的时候包含所有的名字，匹配exclude的名字总是被排除
+#[derive(Debug, Clone, Default)]
+pub struct NameFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl NameFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<NameFilter, regex::Error> {
+        Ok(NameFilter {
+            include: Self::compile(include)?,
+            exclude: Self::compile(exclude)?,
+        })
+    }
+
+    fn compile(patterns: &[String]) -> Result<Vec<Regex>, regex::Error> {
+        patterns
+            .iter()
+            .map(|p| Regex::new(&format!("^(?:{})$", p)))
+            .collect()
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(name)))
+            && !self.exclude.iter().any(|r| r.is_match(name))
+    }
+}
+
+///
+/// 把逗号分隔的正则表达式列表拆开，只在最外层的逗号处拆分：
+/// `{1,3}`、`[a,b]`、`(a|b,c)`中的逗号以及转义的`\,`属于正则表达式本身，忽略空项
+pub fn split_patterns(value: &str) -> Vec<String> {
+    let mut patterns = vec![];
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut in_class = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if in_class {
+            in_class = c != ']';
+        } else {
+            match c {
+                '[' => in_class = true,
+                '(' | '{' => depth += 1,
+                ')' | '}' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    patterns.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        current.push(c);
+    }
+    patterns.push(current);
+    patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_filter() {
+        let filter = NameFilter::new(
+            &["order_.*".to_string(), "pay".to_string()],
+            &[".*_test".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches("order_created"));
+        assert!(filter.matches("pay"));
+        assert!(!filter.matches("payment"));
+        assert!(!filter.matches("order_test"));
+        assert!(!filter.matches("user_created"));
+        assert!(NameFilter::default().matches("anything"));
+        assert!(NameFilter::new(&["(".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_split_patterns() {
+        assert_eq!(
+            split_patterns("order_.*, pay_[0-9]{1,3},,[a,b]x,(c|d,e)\\,f "),
+            vec!["order_.*", "pay_[0-9]{1,3}", "[a,b]x", "(c|d,e)\\,f"]
+        );
+        let filter = NameFilter::new(&split_patterns("pay_[0-9]{1,3}"), &[]).unwrap();
+        assert!(filter.matches("pay_12"));
+        assert!(!filter.matches("pay_1234"));
+        assert!(split_patterns(" ").is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::{iter::Peekable, str::Chars};
+
 #[derive(Debug, Clone)]
 pub enum TokenType {
     BeginObject(char),
@@ -24,7 +26,7 @@ pub struct Tokenizer {
 impl Tokenizer {
     pub fn new(source: String) -> Tokenizer {
         Tokenizer {
-            source: source,
+            source,
             tokens: vec![],
         }
     }
@@ -34,12 +36,11 @@ impl Tokenizer {
     }
 
     pub fn parse(&mut self) {
-        let chars = self.source.chars();
-        let mut iter = chars.enumerate();
+        let mut iter = self.source.chars().peekable();
 
         loop {
             match iter.next() {
-                Some((_i, c)) => match c {
+                Some(c) => match c {
                     '{' => self.tokens.push(TokenType::BeginObject('{')),
                     '}' => self.tokens.push(TokenType::EndObject('}')),
                     '[' => self.tokens.push(TokenType::BeginArray('[')),
@@ -47,14 +48,18 @@ impl Tokenizer {
                     ',' => self.tokens.push(TokenType::SepComma(',')),
                     ':' => self.tokens.push(TokenType::SepColon(':')),
                     '0'..='9' | '-' => {
-                        let (first, second) = self.parse_number(c, &mut iter);
-                        self.tokens.push(first);
-                        self.tokens.push(second);
+                        let token = self.parse_number(c, &mut iter);
+                        self.tokens.push(token);
                     }
                     '"' => {
                         let token = self.parse_string(&mut iter);
                         self.tokens.push(token);
                     }
+                    't' | 'f' | 'n' => {
+                        if let Some(token) = self.parse_literal(c, &mut iter) {
+                            self.tokens.push(token);
+                        }
+                    }
                     _ => continue,
                 },
                 None => {
@@ -65,12 +70,20 @@ impl Tokenizer {
         }
     }
 
-    fn parse_string<T: Iterator<Item = (usize, char)>>(&self, iter: &mut T) -> TokenType {
+    ///
+    /// 解析字符串，转义字符原样保留，输出JSON的时候不需要重新转义
+    fn parse_string(&self, iter: &mut Peekable<Chars>) -> TokenType {
         let mut value = String::from("");
         loop {
             match iter.next() {
-                Some((_i, c)) => match c {
+                Some(c) => match c {
                     '"' => return TokenType::StringValue(value),
+                    '\\' => {
+                        value.push(c);
+                        if let Some(escaped) = iter.next() {
+                            value.push(escaped);
+                        }
+                    }
                     _ => value.push(c),
                 },
                 None => {
@@ -80,27 +93,29 @@ impl Tokenizer {
         }
     }
 
-    fn parse_number<T: Iterator<Item = (usize, char)>>(
-        &self,
-        first: char,
-        iter: &mut T,
-    ) -> (TokenType, TokenType) {
+    ///
+    /// 解析数字，包括小数和科学计数法，分隔符留给下一次解析
+    fn parse_number(&self, first: char, iter: &mut Peekable<Chars>) -> TokenType {
         let mut value = String::from("");
         value.push(first);
-        loop {
-            match iter.next() {
-                Some((_i, c)) => match c {
-                    '0'..='9' => value.push(c),
-                    ':' => return (TokenType::Number(value), TokenType::SepColon(c)),
-                    ',' => return (TokenType::Number(value), TokenType::SepComma(c)),
-                    '}' => return (TokenType::Number(value), TokenType::EndObject(c)),
-                    ']' => return (TokenType::Number(value), TokenType::EndArray(c)),
-                    _ => return (TokenType::Number(value), TokenType::EndDocument),
-                },
-                None => {
-                    return (TokenType::Number(value), TokenType::EndDocument);
-                }
-            }
+        while let Some(c) = iter.next_if(|c| matches!(c, '0'..='9' | '.' | 'e' | 'E' | '+' | '-')) {
+            value.push(c);
+        }
+        TokenType::Number(value)
+    }
+
+    ///
+    /// 解析true、false、null
+    fn parse_literal(&self, first: char, iter: &mut Peekable<Chars>) -> Option<TokenType> {
+        let mut value = String::from("");
+        value.push(first);
+        while let Some(c) = iter.next_if(|c| c.is_ascii_alphabetic()) {
+            value.push(c);
+        }
+        match value.as_str() {
+            "true" | "false" => Some(TokenType::Boolean(value)),
+            "null" => Some(TokenType::Null(value)),
+            _ => None,
         }
     }
 
@@ -108,10 +123,62 @@ impl Tokenizer {
     /// 正规化JSON，采用解析JSON的方案来处理
     pub fn regular_json(&mut self) -> String {
         self.parse();
-        Self::do_regular_json(&mut self.tokens)
+        Self::do_regular_json(&Self::stringify_object_keys(&self.tokens))
+    }
+
+    ///
+    /// fastjson会把对象作为Map的key，例如: {{"brokerName":"broker-a","queueId":0,"topic":"T"}:{...}}，
+    /// 把这种key转换为JSON字符串，字符串的内容是正规化以后的对象
+    pub fn stringify_object_keys(tokens: &[TokenType]) -> Vec<TokenType> {
+        let mut result: Vec<TokenType> = vec![];
+        let mut stack = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            let expect_key = matches!(stack.last(), Some('{'))
+                && matches!(
+                    result.last(),
+                    Some(TokenType::BeginObject(_)) | Some(TokenType::SepComma(_))
+                );
+            match &tokens[i] {
+                TokenType::BeginObject(_) if expect_key => {
+                    let end = Self::matching_end_object(tokens, i);
+                    let key = Self::do_regular_json(&Self::stringify_object_keys(&tokens[i..=end]));
+                    result.push(TokenType::StringValue(
+                        key.replace('\\', "\\\\").replace('"', "\\\""),
+                    ));
+                    i = end + 1;
+                    continue;
+                }
+                TokenType::BeginObject(c) | TokenType::BeginArray(c) => stack.push(*c),
+                TokenType::EndObject(_) | TokenType::EndArray(_) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+            result.push(tokens[i].clone());
+            i += 1;
+        }
+        result
     }
 
-    pub fn do_regular_json(tokens: &Vec<TokenType>) -> String {
+    fn matching_end_object(tokens: &[TokenType], begin: usize) -> usize {
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(begin) {
+            match token {
+                TokenType::BeginObject(_) => depth += 1,
+                TokenType::EndObject(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        tokens.len() - 1
+    }
+
+    pub fn do_regular_json(tokens: &[TokenType]) -> String {
         let mut json = String::from("");
         let mut iter = tokens.iter();
         loop {
@@ -129,7 +196,7 @@ impl Tokenizer {
                                 let mut temp = String::from("\"");
                                 temp.push_str(s.as_str());
                                 temp.push('"');
-                                json.push_str(&temp.as_str());
+                                json.push_str(temp.as_str());
                                 json.push(*c);
                             }
                             TokenType::EndObject(c)
@@ -152,7 +219,7 @@ impl Tokenizer {
                                 let mut temp = String::from("\"");
                                 temp.push_str(s.as_str());
                                 temp.push('"');
-                                json.push_str(&temp.as_str());
+                                json.push_str(temp.as_str());
                                 json.push(*c);
                             }
                             TokenType::EndObject(c)
@@ -240,4 +307,18 @@ mod tests {
         tokenizer.parse();
         println!("打印Tokenizer:{:?}", tokenizer.tokens);
     }
+
+    #[test]
+    fn test_regular_fastjson() {
+        let json = r#"{"mqTable":{{"brokerName":"broker-a","queueId":0,"topic":"T"}:{"locked":false,"pullRT":0.5,"offset":-1}},"jstack":"\"main\" RUNNABLE","enable":true,"remark":null}"#;
+        let result = Tokenizer::new(json.to_string()).regular_json();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let key = r#"{"brokerName":"broker-a","queueId":0,"topic":"T"}"#;
+        assert_eq!(value["mqTable"][key]["locked"], false);
+        assert_eq!(value["mqTable"][key]["pullRT"], 0.5);
+        assert_eq!(value["mqTable"][key]["offset"], -1);
+        assert_eq!(value["jstack"], "\"main\" RUNNABLE");
+        assert_eq!(value["enable"], true);
+        assert!(value["remark"].is_null());
+    }
 }
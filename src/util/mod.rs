@@ -1 +1,7 @@
+//!
+//! 解析Broker返回的非标准JSON，以及Topic分类、名字过滤和表格输出等工具
+
+pub mod filter;
 pub mod json;
+pub mod table;
+pub mod topic;
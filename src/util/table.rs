@@ -0,0 +1,120 @@
+use serde_json::{Map, Value};
+
+///
+/// 命令行输出的表格，文本格式按列对齐，JSON格式是以列名为key的对象数组
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Table {
+        Table {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: vec![],
+        }
+    }
+
+    ///
+    /// 添加一行，列数和表头不一致的时候多余的列被忽略，缺少的列为空
+    pub fn add_row(&mut self, mut row: Vec<String>) {
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    ///
+    /// 按照列的最大宽度左对齐，列之间用两个空格分隔
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in self.rows.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+        let mut out = String::new();
+        for row in std::iter::once(&self.headers).chain(self.rows.iter()) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            out.push_str(line.join("  ").trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .headers
+                        .iter()
+                        .cloned()
+                        .zip(row.iter().map(|cell| Value::String(cell.clone())))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    ///
+    /// 每一行是一个以列名为key的JSON对象，方便使用jq等工具逐行处理
+    pub fn render_json(&self) -> String {
+        let mut out = String::new();
+        if let Value::Array(rows) = self.to_json() {
+            for row in rows {
+                out.push_str(&row.to_string());
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    ///
+    /// 打印到标准输出，json为true的时候每一行输出一个JSON对象
+    pub fn print(&self, json: bool) {
+        if json {
+            print!("{}", self.render_json());
+        } else {
+            print!("{}", self.render());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table() {
+        let mut table = Table::new(&["broker", "addr"]);
+        table.add_row(vec![
+            "broker-a".to_string(),
+            "10.20.141.73:10911".to_string(),
+        ]);
+        table.add_row(vec!["b".to_string()]);
+        assert_eq!(
+            table.render(),
+            "broker    addr\n\
+             broker-a  10.20.141.73:10911\n\
+             b\n"
+        );
+        assert_eq!(
+            table.to_json(),
+            serde_json::json!([
+                {"broker": "broker-a", "addr": "10.20.141.73:10911"},
+                {"broker": "b", "addr": ""}
+            ])
+        );
+        assert_eq!(
+            table.render_json(),
+            "{\"addr\":\"10.20.141.73:10911\",\"broker\":\"broker-a\"}\n\
+             {\"addr\":\"\",\"broker\":\"b\"}\n"
+        );
+    }
+}
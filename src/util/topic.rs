@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+///
+/// 消费者组的重试Topic的前缀，重试Topic的名字是%RETRY%{group}
+pub const RETRY_GROUP_TOPIC_PREFIX: &str = "%RETRY%";
+///
+/// 消费者组的死信Topic的前缀，死信Topic的名字是%DLQ%{group}
+pub const DLQ_GROUP_TOPIC_PREFIX: &str = "%DLQ%";
+///
+/// Broker内置的系统Topic，和RocketMQ的TopicValidator中的系统Topic一致。
+/// 系统Topic列表请求失败或者旧版本的Broker没有返回的时候也能识别
+pub const BUILTIN_SYSTEM_TOPICS: [&str; 9] = [
+    "TBW102",
+    "SCHEDULE_TOPIC_XXXX",
+    "BenchmarkTest",
+    "RMQ_SYS_TRANS_HALF_TOPIC",
+    "RMQ_SYS_TRACE_TOPIC",
+    "RMQ_SYS_TRANS_OP_HALF_TOPIC",
+    "TRANS_CHECK_MAX_TIME_TOPIC",
+    "SELF_TEST_TOPIC",
+    "OFFSET_MOVED_EVENT",
+];
+///
+/// 5.x的系统Topic的前缀，例如: rmq_sys_SYNC_BROKER_MEMBER_broker-a
+pub const SYSTEM_TOPIC_PREFIX: &str = "rmq_sys_";
+
+///
+/// Topic的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicType {
+    Normal,
+    Retry,
+    Dlq,
+    System,
+}
+
+impl TopicType {
+    ///
+    /// 根据Topic的名字判断类型
+    pub fn of(topic: &str) -> TopicType {
+        if topic.starts_with(RETRY_GROUP_TOPIC_PREFIX) {
+            TopicType::Retry
+        } else if topic.starts_with(DLQ_GROUP_TOPIC_PREFIX) {
+            TopicType::Dlq
+        } else {
+            TopicType::Normal
+        }
+    }
+
+    ///
+    /// 先根据名字判断重试和死信Topic，其余的Topic出现在系统Topic列表中，或者是内置的系统Topic的时候是系统Topic
+    pub fn classify(topic: &str, system_topics: &HashSet<String>) -> TopicType {
+        match TopicType::of(topic) {
+            TopicType::Normal
+                if system_topics.contains(topic)
+                    || BUILTIN_SYSTEM_TOPICS.contains(&topic)
+                    || topic.starts_with(SYSTEM_TOPIC_PREFIX) =>
+            {
+                TopicType::System
+            }
+            topic_type => topic_type,
+        }
+    }
+
+    ///
+    /// 作为指标的topic_type标签的值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TopicType::Normal => "normal",
+            TopicType::Retry => "retry",
+            TopicType::Dlq => "dlq",
+            TopicType::System => "system",
+        }
+    }
+}
+
+///
+/// 重试Topic和死信Topic所属的消费者组，普通Topic返回None
+pub fn consumer_group_of(topic: &str) -> Option<&str> {
+    topic
+        .strip_prefix(RETRY_GROUP_TOPIC_PREFIX)
+        .or_else(|| topic.strip_prefix(DLQ_GROUP_TOPIC_PREFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_type() {
+        assert_eq!(TopicType::of("%RETRY%order_group"), TopicType::Retry);
+        assert_eq!(TopicType::of("%DLQ%order_group"), TopicType::Dlq);
+        assert_eq!(TopicType::of("order_topic"), TopicType::Normal);
+        assert_eq!(consumer_group_of("%DLQ%order_group"), Some("order_group"));
+        assert_eq!(consumer_group_of("order_topic"), None);
+    }
+
+    #[test]
+    fn test_classify_system_topic() {
+        let system_topics: HashSet<String> = ["TBW102", "%RETRY%order_group"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            TopicType::classify("TBW102", &system_topics),
+            TopicType::System
+        );
+        assert_eq!(
+            TopicType::classify("%RETRY%order_group", &system_topics),
+            TopicType::Retry
+        );
+        assert_eq!(
+            TopicType::classify("order_topic", &system_topics),
+            TopicType::Normal
+        );
+        assert_eq!(TopicType::System.as_str(), "system");
+
+        // 系统Topic列表为空的时候使用内置的系统Topic
+        let empty = HashSet::new();
+        for topic in [
+            "RMQ_SYS_TRACE_TOPIC",
+            "SCHEDULE_TOPIC_XXXX",
+            "TBW102",
+            "OFFSET_MOVED_EVENT",
+            "rmq_sys_SYNC_BROKER_MEMBER_broker-a",
+        ] {
+            assert_eq!(TopicType::classify(topic, &empty), TopicType::System);
+        }
+        assert_eq!(
+            TopicType::classify("order_topic", &empty),
+            TopicType::Normal
+        );
+    }
+}